@@ -0,0 +1,38 @@
+//! Debug tooling, only built with the `dev` cargo feature.
+//!
+//! Everything here is for whoever works on the game: the world inspector, physics debug draw
+//! (toggled with F3) and the [developer console](crate::game::console), whose commands cover
+//! cheats like `give_xp` and `set_gravity`. Release builds are made without the feature and
+//! carry none of it.
+
+use bevy::prelude::*;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::game::console::ConsolePlugin;
+use crate::game::input::{Action, ActionState};
+
+fn startup_disable_debug_view(mut debug_config: ResMut<PhysicsDebugConfig>) {
+    debug_config.enabled = false;
+}
+
+fn toggle_debug_view(mut debug_config: ResMut<PhysicsDebugConfig>, action_state: Res<ActionState>) {
+    if action_state.just_pressed(Action::Debug) {
+        debug_config.enabled = !debug_config.enabled;
+    }
+}
+
+/// Adds the debug tooling, after [`GamePlugin`](crate::GamePlugin) and `DefaultPlugins`.
+pub struct DevPlugin;
+
+impl Plugin for DevPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            WorldInspectorPlugin::default(),
+            PhysicsDebugPlugin::new(FixedUpdate),
+            ConsolePlugin,
+        ))
+        .add_systems(Startup, startup_disable_debug_view)
+        .add_systems(Update, toggle_debug_view);
+    }
+}
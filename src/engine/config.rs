@@ -0,0 +1,77 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::log::{info, warn};
+use serde::{de::DeserializeOwned, Serialize};
+
+const APP_DIR: &str = "risk-of-rust";
+
+/// The path of a config file in the user's config directory, e.g. `~/.config/risk-of-rust/`.
+pub fn config_path(file_name: &str) -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(file_name))
+}
+
+/// The path of a save file in the user's data directory, e.g. `~/.local/share/risk-of-rust/`.
+pub fn data_path(file_name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR).join(file_name))
+}
+
+/// Replaces the file at `path` with `contents`, creating its directory if needed.
+///
+/// The contents are written to a temporary file next to it first and then renamed over it, so
+/// a crash halfway through leaves either the old file or the new one, never a mix.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+/// Loads a RON config file, falling back to the default if it is missing or invalid.
+pub fn load_config<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    let Some(path) = config_path(file_name) else {
+        return T::default();
+    };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return T::default();
+    };
+
+    match ron::from_str(&contents) {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("Ignoring invalid config file {}: {err}", path.display());
+            T::default()
+        }
+    }
+}
+
+/// Writes a config file as RON. Failures are logged, a missing config is never fatal.
+pub fn save_config<T: Serialize>(file_name: &str, value: &T) {
+    let Some(path) = config_path(file_name) else {
+        warn!("No config directory available, not saving {file_name}");
+        return;
+    };
+
+    let contents = match ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("Failed to serialize {file_name}: {err}");
+            return;
+        }
+    };
+
+    match write_atomic(&path, contents.as_bytes()) {
+        Ok(()) => info!("Saved {}", path.display()),
+        Err(err) => warn!("Failed to write {}: {err}", path.display()),
+    }
+}
@@ -0,0 +1,96 @@
+use bevy::{
+    prelude::*,
+    window::{PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+
+use super::frame_pacing::FrameLimit;
+use super::pixel_camera::GameResolution;
+use crate::game::settings::Settings;
+
+/// The window modes offered in the settings screen, in order.
+pub const WINDOW_MODES: [WindowMode; 3] = [
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::Fullscreen,
+];
+
+/// The largest canvas scales offered in the settings screen, in order. `None` scales up as
+/// far as the window allows.
+pub const MAX_SCALES: [Option<u32>; 7] =
+    [None, Some(1), Some(2), Some(3), Some(4), Some(5), Some(6)];
+
+/// The preset `steps` away from `current`, wrapping around. Values that aren't presets, e.g.
+/// edited into the config by hand, start from the first one.
+pub fn cycle_preset<T: Copy + PartialEq>(presets: &[T], current: T, steps: i32) -> T {
+    let len = presets.len() as i32;
+    let index = presets
+        .iter()
+        .position(|preset| *preset == current)
+        .unwrap_or(0) as i32;
+    presets[(index + steps).rem_euclid(len) as usize]
+}
+
+pub fn window_mode_label(mode: WindowMode) -> &'static str {
+    match mode {
+        WindowMode::Windowed => "Windowed",
+        WindowMode::BorderlessFullscreen => "Borderless",
+        WindowMode::SizedFullscreen | WindowMode::Fullscreen => "Fullscreen",
+    }
+}
+
+pub fn max_scale_label(max_scale: Option<u32>) -> String {
+    match max_scale {
+        Some(scale) => format!("{scale}x"),
+        None => "Auto".to_string(),
+    }
+}
+
+/// The display part of the [`Settings`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub window_mode: WindowMode,
+    pub frame_limit: FrameLimit,
+    pub resolution: GameResolution,
+    /// The most the canvas is scaled up by, if less than fits the window.
+    pub max_scale: Option<u32>,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            window_mode: WindowMode::Windowed,
+            frame_limit: FrameLimit::Vsync,
+            resolution: GameResolution::default(),
+            max_scale: None,
+        }
+    }
+}
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let mode = settings.display.window_mode;
+    let present_mode = settings.display.frame_limit.present_mode();
+    for mut window in &mut windows {
+        if window.mode != mode {
+            window.mode = mode;
+        }
+        if window.present_mode != present_mode {
+            window.present_mode = present_mode;
+        }
+    }
+}
+
+pub struct DisplayPlugin;
+
+impl Plugin for DisplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            apply_window_settings.run_if(resource_changed::<Settings>()),
+        );
+    }
+}
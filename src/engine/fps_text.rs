@@ -1,3 +1,5 @@
+use std::collections::VecDeque;
+
 use crate::assets::GameFont;
 
 use bevy::{
@@ -5,6 +7,31 @@ use bevy::{
     prelude::*,
 };
 
+/// How many recent frames the percentiles are taken over.
+const FRAME_TIME_HISTORY: usize = 240;
+
+/// The real duration of recent frames in milliseconds, newest last.
+#[derive(Resource, Default)]
+pub struct FrameTimes(VecDeque<f32>);
+
+impl FrameTimes {
+    /// The frame time that `percent` percent of recent frames were at or under.
+    pub fn percentile(&self, percent: f32) -> Option<f32> {
+        let mut sorted: Vec<f32> = self.0.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let last = sorted.len().checked_sub(1)?;
+        let index = ((percent / 100.0) * last as f32).round() as usize;
+        sorted.get(index.min(last)).copied()
+    }
+}
+
+pub fn record_frame_times(time: Res<Time<Real>>, mut frame_times: ResMut<FrameTimes>) {
+    if frame_times.0.len() == FRAME_TIME_HISTORY {
+        frame_times.0.pop_front();
+    }
+    frame_times.0.push_back(time.delta_seconds() * 1000.0);
+}
+
 // A unit struct to help identify the FPS UI component, since there may be many Text components
 #[derive(Component)]
 pub struct FpsText;
@@ -35,14 +62,36 @@ pub fn spawn_fps_text(mut commands: Commands, game_font: Res<GameFont>) {
 
 pub fn text_update_system(
     diagnostics: Res<DiagnosticsStore>,
+    frame_times: Res<FrameTimes>,
     mut fps_text: Query<&mut Text, With<FpsText>>,
 ) {
     for mut text in &mut fps_text {
         if let Some(fps) = diagnostics.get(FrameTimeDiagnosticsPlugin::FPS) {
             if let Some(value) = fps.smoothed() {
-                // Update the value of the second section
-                text.sections[0].value = format!("FPS: {value:.0}");
+                // Spikes hide in an average, so show how slow the slowest frames were too.
+                let percentiles = [50.0, 95.0, 99.0]
+                    .map(|percent| frame_times.percentile(percent).unwrap_or_default());
+                text.sections[0].value = format!(
+                    "FPS: {value:.0} p50 {:.1}ms p95 {:.1}ms p99 {:.1}ms",
+                    percentiles[0], percentiles[1], percentiles[2]
+                );
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_pick_slow_frames() {
+        let mut frame_times = FrameTimes::default();
+        assert_eq!(frame_times.percentile(50.0), None);
+
+        frame_times.0.extend((0..99).map(|_| 16.0));
+        frame_times.0.push_back(50.0);
+        assert_eq!(frame_times.percentile(50.0), Some(16.0));
+        assert_eq!(frame_times.percentile(100.0), Some(50.0));
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::{Duration, Instant};
+
+use bevy::{prelude::*, window::PresentMode};
+use serde::{Deserialize, Serialize};
+
+use super::display::cycle_preset;
+use crate::game::settings::Settings;
+
+/// The last stretch of a frame is spun rather than slept, OS sleeps overshoot by about a
+/// millisecond.
+const SPIN_MARGIN: Duration = Duration::from_millis(1);
+
+/// How the frame rate is limited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameLimit {
+    /// Wait for the display's refresh.
+    Vsync,
+    /// Present immediately, but sleep so at most this many frames are drawn per second.
+    Capped(u32),
+    Unlimited,
+}
+
+impl FrameLimit {
+    /// The choices offered in the settings screen, in order.
+    pub const PRESETS: [FrameLimit; 7] = [
+        FrameLimit::Vsync,
+        FrameLimit::Capped(30),
+        FrameLimit::Capped(60),
+        FrameLimit::Capped(120),
+        FrameLimit::Capped(144),
+        FrameLimit::Capped(240),
+        FrameLimit::Unlimited,
+    ];
+
+    /// The preset `steps` away from this one, wrapping around.
+    pub fn cycle(self, steps: i32) -> Self {
+        cycle_preset(&Self::PRESETS, self, steps)
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            FrameLimit::Vsync => "Vsync".to_string(),
+            FrameLimit::Capped(fps) => format!("{fps} FPS"),
+            FrameLimit::Unlimited => "Unlimited".to_string(),
+        }
+    }
+
+    pub(super) fn present_mode(self) -> PresentMode {
+        match self {
+            FrameLimit::Vsync => PresentMode::AutoVsync,
+            FrameLimit::Capped(_) | FrameLimit::Unlimited => PresentMode::AutoNoVsync,
+        }
+    }
+
+    fn frame_duration(self) -> Option<Duration> {
+        match self {
+            FrameLimit::Capped(fps) if fps > 0 => Some(Duration::from_secs_f64(1.0 / fps as f64)),
+            _ => None,
+        }
+    }
+}
+
+/// When the previous frame was let go, to measure the next one against.
+#[derive(Resource)]
+struct FrameLimiter {
+    last_frame: Instant,
+}
+
+/// Holds the frame back until the frame cap allows the next one.
+fn limit_frame_rate(settings: Res<Settings>, mut limiter: ResMut<FrameLimiter>) {
+    if let Some(frame_duration) = settings.display.frame_limit.frame_duration() {
+        let target = limiter.last_frame + frame_duration;
+        let now = Instant::now();
+        if target > now + SPIN_MARGIN {
+            std::thread::sleep(target - now - SPIN_MARGIN);
+        }
+        while Instant::now() < target {
+            std::hint::spin_loop();
+        }
+        // Aim the next frame from the target rather than from now, so an overshoot isn't
+        // carried over, unless the frame ran long and there's nothing to catch up with.
+        limiter.last_frame = target.max(Instant::now() - frame_duration);
+    } else {
+        limiter.last_frame = Instant::now();
+    }
+}
+
+pub struct FramePacingPlugin;
+
+impl Plugin for FramePacingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FrameLimiter {
+            last_frame: Instant::now(),
+        })
+        .add_systems(Last, limit_frame_rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_cycle_both_ways() {
+        assert_eq!(FrameLimit::Vsync.cycle(1), FrameLimit::Capped(30));
+        assert_eq!(FrameLimit::Vsync.cycle(-1), FrameLimit::Unlimited);
+        assert_eq!(FrameLimit::Unlimited.cycle(1), FrameLimit::Vsync);
+        // Limits edited into the config by hand start from the first preset.
+        assert_eq!(FrameLimit::Capped(75).cycle(1), FrameLimit::Capped(30));
+    }
+}
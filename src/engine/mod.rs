@@ -1,2 +1,7 @@
 pub mod animation;
+pub mod config;
+pub mod display;
 pub mod fps_text;
+pub mod frame_pacing;
+pub mod pixel_camera;
+pub mod rng;
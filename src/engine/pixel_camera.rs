@@ -0,0 +1,255 @@
+use bevy::{
+    core_pipeline::clear_color::ClearColorConfig,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        view::{RenderLayers, VisibilitySystems},
+    },
+    transform::TransformSystem,
+    window::PrimaryWindow,
+};
+use serde::{Deserialize, Serialize};
+
+use super::display::cycle_preset;
+use crate::game::settings::Settings;
+use crate::{GAME_HEIGHT, GAME_WIDTH};
+
+/// The layer the window camera and the upscaled canvas are on, everything else is on layer 0.
+const CANVAS_LAYER: u8 = 1;
+
+/// Extra texels rendered around the edge of the canvas, so the subpixel shift never shows
+/// past the edge of the image.
+const CANVAS_MARGIN: u32 = 1;
+
+const LETTERBOX_COLOR: Color = Color::BLACK;
+
+/// The size of the low resolution image the world is drawn at.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameResolution {
+    Small,
+    #[default]
+    Standard,
+    Large,
+}
+
+impl GameResolution {
+    /// The choices offered in the settings screen, in order.
+    pub const PRESETS: [GameResolution; 3] = [
+        GameResolution::Small,
+        GameResolution::Standard,
+        GameResolution::Large,
+    ];
+
+    pub fn size(self) -> UVec2 {
+        match self {
+            GameResolution::Small => UVec2::new(240, 160),
+            GameResolution::Standard => UVec2::new(GAME_WIDTH as u32, GAME_HEIGHT as u32),
+            GameResolution::Large => UVec2::new(480, 360),
+        }
+    }
+
+    /// The preset `steps` away from this one, wrapping around.
+    pub fn cycle(self, steps: i32) -> Self {
+        cycle_preset(&Self::PRESETS, self, steps)
+    }
+
+    pub fn label(self) -> String {
+        let size = self.size();
+        format!("{}x{}", size.x, size.y)
+    }
+}
+
+/// The camera that draws the world, into the canvas rather than the window.
+#[derive(Component)]
+pub struct GameCamera;
+
+/// The sprite showing the world on screen, scaled up by a whole number.
+#[derive(Component)]
+struct Canvas;
+
+fn canvas_image(size: UVec2) -> Image {
+    let size = Extent3d {
+        width: size.x + CANVAS_MARGIN * 2,
+        height: size.y + CANVAS_MARGIN * 2,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("canvas"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    // Fills the data with zeros.
+    image.resize(size);
+    image
+}
+
+fn spawn_cameras(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    settings: Res<Settings>,
+) {
+    let canvas = images.add(canvas_image(settings.display.resolution.size()));
+
+    // One world unit is one texel of the canvas, at a projection scale of 1.
+    commands.spawn((
+        Name::new("GameCamera"),
+        Camera2dBundle {
+            camera: Camera {
+                order: -1,
+                target: RenderTarget::Image(canvas.clone()),
+                ..default()
+            },
+            ..default()
+        },
+        UiCameraConfig { show_ui: false },
+        GameCamera,
+    ));
+
+    // The window camera only sees the canvas, and draws the UI at the window's resolution.
+    commands.spawn((
+        Name::new("WindowCamera"),
+        Camera2dBundle {
+            camera_2d: Camera2d {
+                clear_color: ClearColorConfig::Custom(LETTERBOX_COLOR),
+            },
+            ..default()
+        },
+        RenderLayers::layer(CANVAS_LAYER),
+    ));
+
+    commands.spawn((
+        Name::new("Canvas"),
+        SpriteBundle {
+            texture: canvas,
+            ..default()
+        },
+        RenderLayers::layer(CANVAS_LAYER),
+        Canvas,
+    ));
+}
+
+fn resize_canvas(
+    settings: Res<Settings>,
+    canvases: Query<&Handle<Image>, With<Canvas>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let size = settings.display.resolution.size();
+    for handle in &canvases {
+        if let Some(image) = images.get_mut(handle) {
+            if image.size() != size + UVec2::splat(CANVAS_MARGIN * 2) {
+                *image = canvas_image(size);
+            }
+        }
+    }
+}
+
+/// Scales the canvas up by the largest whole number that fits the window and centers it on
+/// whole physical pixels, leaving the rest of the window letterboxed.
+fn fit_canvas(
+    settings: Res<Settings>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut canvases: Query<(&mut Sprite, &mut Transform), With<Canvas>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let resolution = settings.display.resolution.size();
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    let fit = (window_size / resolution).min_element();
+    let factor = settings
+        .display
+        .max_scale
+        .map_or(fit, |max_scale| fit.min(max_scale))
+        .max(1);
+    let canvas_size = resolution * factor;
+
+    // The window camera is centered, with one world unit per logical pixel.
+    let scale_factor = window.scale_factor() as f32;
+    let corner = (window_size.as_ivec2() - canvas_size.as_ivec2()) / 2;
+    let center = corner.as_vec2() + canvas_size.as_vec2() / 2.0 - window_size.as_vec2() / 2.0;
+    let translation = Vec3::new(center.x, -center.y, 0.0) / scale_factor;
+    let custom_size = Some(canvas_size.as_vec2() / scale_factor);
+
+    for (mut sprite, mut transform) in &mut canvases {
+        if sprite.custom_size != custom_size {
+            sprite.custom_size = custom_size;
+        }
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
+
+/// Draws the world from the nearest whole texel, so sprites don't shimmer as the camera
+/// moves, and makes up the difference by shifting which part of the canvas is shown.
+///
+/// Only touches the `GlobalTransform`, so the camera keeps moving smoothly underneath.
+fn snap_game_camera(
+    settings: Res<Settings>,
+    mut cameras: Query<(&mut GlobalTransform, &OrthographicProjection), With<GameCamera>>,
+    mut canvases: Query<&mut Sprite, With<Canvas>>,
+) {
+    let Ok((mut global_transform, projection)) = cameras.get_single_mut() else {
+        return;
+    };
+    let texel = projection.scale;
+    let mut transform = global_transform.compute_transform();
+    let exact = transform.translation.truncate();
+    let snapped = (exact / texel).round() * texel;
+    transform.translation = snapped.extend(transform.translation.z);
+    *global_transform = transform.into();
+
+    // Texture coordinates run downwards.
+    let subpixel = (exact - snapped) / texel;
+    let min = Vec2::splat(CANVAS_MARGIN as f32) + Vec2::new(subpixel.x, -subpixel.y);
+    let rect = Some(Rect::from_corners(
+        min,
+        min + settings.display.resolution.size().as_vec2(),
+    ));
+    for mut sprite in &mut canvases {
+        sprite.rect = rect;
+    }
+}
+
+pub struct PixelCameraPlugin;
+
+impl Plugin for PixelCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_cameras)
+            .add_systems(Update, resize_canvas.run_if(resource_changed::<Settings>()))
+            .add_systems(
+                PostUpdate,
+                (
+                    fit_canvas.before(TransformSystem::TransformPropagate),
+                    snap_game_camera
+                        .after(TransformSystem::TransformPropagate)
+                        .before(VisibilitySystems::UpdateOrthographicFrusta),
+                ),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolutions_cycle_both_ways() {
+        assert_eq!(GameResolution::Standard.cycle(1), GameResolution::Large);
+        assert_eq!(GameResolution::Small.cycle(-1), GameResolution::Large);
+        assert_eq!(GameResolution::default().label(), "320x240");
+    }
+}
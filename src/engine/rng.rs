@@ -0,0 +1,44 @@
+use bevy::reflect::Reflect;
+
+/// A small, seedable pseudo random number generator (SplitMix64).
+///
+/// Gameplay code should never reach for a global or thread-local RNG, every roll has to be
+/// reproducible from a seed so that runs (and tests) can be replayed exactly.
+#[derive(Clone, Debug, PartialEq, Eq, Reflect)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a float in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns a float in `[min, max)`.
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Returns an index in `[0, len)`. `len` must not be zero.
+    pub fn index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+
+    /// Returns `true` with the given probability, anything `>= 1.0` always succeeds.
+    pub fn chance(&mut self, probability: f32) -> bool {
+        probability > 0.0 && self.next_f32() < probability
+    }
+}
@@ -0,0 +1,386 @@
+use bevy::{asset::io::file::FileAssetReader, audio::Volume, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+use super::combat::DamageDealtEvent;
+use super::player::LevelUpEvent;
+use super::player_controller::JumpEvent;
+use super::projectile::Projectile;
+use super::run_rng::random_seed;
+use super::settings::Settings;
+use crate::engine::rng::Rng;
+use crate::AppState;
+
+/// How long it takes one music track to fade into the next.
+const CROSSFADE_SECS: f32 = 1.5;
+
+/// The volume of each bus, from 0 to 1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl AudioSettings {
+    pub fn music_volume(&self) -> f32 {
+        self.master * self.music
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        self.master * self.sfx
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 0.6,
+            sfx: 0.8,
+        }
+    }
+}
+
+/// A sound effect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Jump,
+    Shoot,
+    Hit,
+    Crit,
+    LevelUp,
+}
+
+struct SfxInfo {
+    path: &'static str,
+    volume: f32,
+    /// How far the playback speed, and with it the pitch, randomly strays from 1.
+    pitch_variation: f32,
+    /// The most instances of the sound that can play at once, more are dropped.
+    max_instances: usize,
+}
+
+impl Sfx {
+    pub const ALL: [Sfx; 5] = [Sfx::Jump, Sfx::Shoot, Sfx::Hit, Sfx::Crit, Sfx::LevelUp];
+
+    fn info(self) -> SfxInfo {
+        let (path, volume, pitch_variation, max_instances) = match self {
+            Sfx::Jump => ("sounds/jump.ogg", 0.6, 0.08, 2),
+            Sfx::Shoot => ("sounds/shoot.ogg", 0.5, 0.1, 4),
+            Sfx::Hit => ("sounds/hit.ogg", 0.7, 0.15, 6),
+            Sfx::Crit => ("sounds/crit.ogg", 0.8, 0.1, 3),
+            Sfx::LevelUp => ("sounds/level_up.ogg", 1.0, 0.0, 1),
+        };
+        SfxInfo {
+            path,
+            volume,
+            pitch_variation,
+            max_instances,
+        }
+    }
+}
+
+/// An event to play a sound effect, gameplay events that make a sound are turned into these.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SfxEvent(pub Sfx);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicTrack {
+    Menu,
+    /// The music of the stage with this number, starting at 1.
+    Stage(u32),
+}
+
+impl MusicTrack {
+    fn path(self) -> String {
+        match self {
+            MusicTrack::Menu => "music/menu.ogg".to_string(),
+            MusicTrack::Stage(stage) => format!("music/stage_{stage}.ogg"),
+        }
+    }
+}
+
+/// The music that should be playing. Changing it crossfades into the new track.
+#[derive(Resource, Default)]
+pub struct Music(pub Option<MusicTrack>);
+
+#[derive(Resource)]
+struct SfxSources(HashMap<Sfx, Handle<AudioSource>>);
+
+/// Rolls the pitch of sound effects, separate from the run's RNG so sounds can't change a run.
+#[derive(Resource)]
+struct SfxRng(Rng);
+
+#[derive(Component)]
+struct SfxInstance(Sfx);
+
+#[derive(Component)]
+struct MusicPlayer {
+    track: MusicTrack,
+    /// How far the track has faded in, from 0 to 1.
+    fade: f32,
+}
+
+/// Whether the file of an audio asset is there to load.
+///
+/// Not every sound and track has been made yet. Loading a missing one would log an error each
+/// time, so they are checked for up front and left silent instead.
+fn audio_exists(path: &str) -> bool {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(path)
+        .exists()
+}
+
+fn load_sfx(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let (present, missing): (Vec<Sfx>, Vec<Sfx>) = Sfx::ALL
+        .into_iter()
+        .partition(|sfx| audio_exists(sfx.info().path));
+    if !missing.is_empty() {
+        info!("Sound effects without a sound yet: {missing:?}");
+    }
+
+    let sources = present
+        .into_iter()
+        .map(|sfx| (sfx, asset_server.load(sfx.info().path)))
+        .collect();
+    commands.insert_resource(SfxSources(sources));
+}
+
+fn gameplay_sfx(
+    mut sfx: EventWriter<SfxEvent>,
+    mut jumps: EventReader<JumpEvent>,
+    mut hits: EventReader<DamageDealtEvent>,
+    mut level_ups: EventReader<LevelUpEvent>,
+    projectiles: Query<(), Added<Projectile>>,
+) {
+    sfx.send_batch(jumps.read().map(|_| SfxEvent(Sfx::Jump)));
+    sfx.send_batch(projectiles.iter().map(|_| SfxEvent(Sfx::Shoot)));
+    sfx.send_batch(
+        hits.read()
+            .map(|hit| SfxEvent(if hit.crit { Sfx::Crit } else { Sfx::Hit })),
+    );
+    sfx.send_batch(level_ups.read().map(|_| SfxEvent(Sfx::LevelUp)));
+}
+
+fn play_sfx(
+    mut commands: Commands,
+    mut events: EventReader<SfxEvent>,
+    sources: Res<SfxSources>,
+    audio_sources: Res<Assets<AudioSource>>,
+    settings: Res<Settings>,
+    mut rng: ResMut<SfxRng>,
+    playing: Query<&SfxInstance>,
+) {
+    let mut counts: HashMap<Sfx, usize> = HashMap::new();
+    for instance in &playing {
+        *counts.entry(instance.0).or_default() += 1;
+    }
+
+    for SfxEvent(sfx) in events.read() {
+        let info = sfx.info();
+        let count = counts.entry(*sfx).or_default();
+        if *count >= info.max_instances {
+            continue;
+        }
+        // A sound that never loaded would never finish, and hold its slot forever.
+        let Some(source) = sources.0.get(sfx).filter(|s| audio_sources.contains(*s)) else {
+            continue;
+        };
+        *count += 1;
+
+        let speed = 1.0 + rng.0.range_f32(-info.pitch_variation, info.pitch_variation);
+        commands.spawn((
+            AudioBundle {
+                source: source.clone(),
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new_absolute(
+                        info.volume * settings.audio.sfx_volume(),
+                    ))
+                    .with_speed(speed),
+            },
+            SfxInstance(*sfx),
+        ));
+    }
+}
+
+fn menu_music(mut music: ResMut<Music>) {
+    music.0 = Some(MusicTrack::Menu);
+}
+
+fn stage_music(mut music: ResMut<Music>) {
+    music.0 = Some(MusicTrack::Stage(1));
+}
+
+fn stop_music(mut music: ResMut<Music>) {
+    music.0 = None;
+}
+
+/// Fades the current track in and every other track out, then despawns them.
+fn crossfade_music(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    time: Res<Time<Real>>,
+    music: Res<Music>,
+    settings: Res<Settings>,
+    mut players: Query<(Entity, &mut MusicPlayer, Option<&AudioSink>)>,
+) {
+    let step = time.delta_seconds() / CROSSFADE_SECS;
+    let mut current_playing = false;
+
+    for (entity, mut player, sink) in &mut players {
+        if Some(player.track) == music.0 {
+            current_playing = true;
+            player.fade = (player.fade + step).min(1.0);
+        } else {
+            player.fade -= step;
+            if player.fade <= 0.0 {
+                commands.entity(entity).despawn();
+                continue;
+            }
+        }
+        // Also follows the volume settings while they're being changed.
+        if let Some(sink) = sink {
+            sink.set_volume(player.fade * settings.audio.music_volume());
+        }
+    }
+
+    if let (Some(track), false) = (music.0, current_playing) {
+        let mut player = commands.spawn((Name::new("Music"), MusicPlayer { track, fade: 0.0 }));
+        // A track that hasn't been made yet still takes part in the fades, silently.
+        if audio_exists(&track.path()) {
+            player.insert(AudioBundle {
+                source: asset_server.load(track.path()),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_absolute(0.0)),
+            });
+        }
+    }
+}
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SfxRng(Rng::new(random_seed())))
+            .init_resource::<Music>()
+            .add_event::<SfxEvent>()
+            .add_systems(Startup, load_sfx)
+            .add_systems(OnEnter(AppState::Menu), menu_music)
+            .add_systems(OnEnter(AppState::InGame), stage_music)
+            .add_systems(OnEnter(AppState::GameOver), stop_music)
+            .add_systems(Update, ((gameplay_sfx, play_sfx).chain(), crossfade_music));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    const FRAME_SECS: f32 = 0.1;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<AudioSource>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                FRAME_SECS,
+            )))
+            .insert_resource(Settings::default())
+            .insert_resource(SfxRng(Rng::new(0)))
+            .insert_resource(SfxSources(HashMap::new()))
+            .init_resource::<Music>()
+            .add_event::<SfxEvent>()
+            .add_systems(Update, (play_sfx, crossfade_music));
+        app
+    }
+
+    fn instances(app: &mut App, sfx: Sfx) -> usize {
+        app.world
+            .query::<&SfxInstance>()
+            .iter(&app.world)
+            .filter(|instance| instance.0 == sfx)
+            .count()
+    }
+
+    fn music_players(app: &mut App) -> Vec<(MusicTrack, f32)> {
+        app.world
+            .query::<&MusicPlayer>()
+            .iter(&app.world)
+            .map(|player| (player.track, player.fade))
+            .collect()
+    }
+
+    #[test]
+    fn sfx_are_limited_to_max_instances() {
+        let mut app = app();
+        let source = app
+            .world
+            .resource_mut::<Assets<AudioSource>>()
+            .add(AudioSource {
+                bytes: Arc::from([]),
+            });
+        app.insert_resource(SfxSources(HashMap::from([
+            (Sfx::Hit, source.clone()),
+            (Sfx::LevelUp, source),
+        ])));
+        let max_hits = Sfx::Hit.info().max_instances;
+
+        for _ in 0..max_hits + 3 {
+            app.world.send_event(SfxEvent(Sfx::Hit));
+        }
+        app.world.send_event(SfxEvent(Sfx::LevelUp));
+        app.update();
+        assert_eq!(instances(&mut app, Sfx::Hit), max_hits);
+        assert_eq!(instances(&mut app, Sfx::LevelUp), 1);
+
+        // Sounds still playing from earlier frames count too.
+        app.world.send_event(SfxEvent(Sfx::Hit));
+        app.update();
+        assert_eq!(instances(&mut app, Sfx::Hit), max_hits);
+
+        // A sound without a loaded source is dropped without taking a slot.
+        app.world.send_event(SfxEvent(Sfx::Jump));
+        app.update();
+        assert_eq!(instances(&mut app, Sfx::Jump), 0);
+    }
+
+    #[test]
+    fn music_crossfades_between_tracks() {
+        let mut app = app();
+        let frames = (CROSSFADE_SECS / FRAME_SECS).ceil() as usize;
+        // The first update only starts the clock.
+        app.update();
+
+        app.world.resource_mut::<Music>().0 = Some(MusicTrack::Menu);
+        app.update();
+        assert_eq!(music_players(&mut app), [(MusicTrack::Menu, 0.0)]);
+        for _ in 0..frames {
+            app.update();
+        }
+        assert_eq!(music_players(&mut app), [(MusicTrack::Menu, 1.0)]);
+
+        app.world.resource_mut::<Music>().0 = Some(MusicTrack::Stage(1));
+        app.update();
+        app.update();
+        let players = music_players(&mut app);
+        let fade = |track| players.iter().find(|(t, _)| *t == track).unwrap().1;
+        assert!(fade(MusicTrack::Menu) < 1.0);
+        assert!(fade(MusicTrack::Stage(1)) > 0.0);
+
+        for _ in 0..frames {
+            app.update();
+        }
+        assert_eq!(music_players(&mut app), [(MusicTrack::Stage(1), 1.0)]);
+
+        app.world.resource_mut::<Music>().0 = None;
+        for _ in 0..=frames {
+            app.update();
+        }
+        assert!(music_players(&mut app).is_empty());
+    }
+}
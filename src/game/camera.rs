@@ -0,0 +1,215 @@
+use bevy::{ecs::query::Has, prelude::*, transform::TransformSystem};
+
+use super::combat::Dead;
+use super::player::Player;
+use super::projectile::ExplosionEvent;
+use super::settings::Settings;
+use crate::engine::pixel_camera::GameCamera;
+use crate::AppState;
+
+/// The furthest the shared camera zooms out to keep every player on screen.
+const MAX_CAMERA_ZOOM: f32 = 1.5;
+/// Space kept between the outermost players and the edge of the screen.
+const CAMERA_FRAME_MARGIN: f32 = 24.0;
+
+/// How quickly the camera closes the gap to where it wants to be, per second.
+const FOLLOW_RATE: f32 = 6.0;
+const ZOOM_RATE: f32 = 6.0;
+const LOOK_AHEAD_RATE: f32 = 2.0;
+/// How far ahead of the players the camera looks in the direction they face.
+const LOOK_AHEAD: f32 = 32.0;
+/// How far the players can move up or down from the middle of the screen before the camera
+/// follows them.
+const DEAD_ZONE_HALF_HEIGHT: f32 = 24.0;
+
+/// The furthest the camera is thrown off at full trauma, in pixels.
+const MAX_SHAKE_OFFSET: f32 = 8.0;
+const SHAKE_FREQUENCY: f32 = 25.0;
+/// Trauma lost per second.
+const TRAUMA_DECAY: f32 = 1.5;
+/// Trauma an explosion adds per pixel of its radius.
+const EXPLOSION_TRAUMA_PER_RADIUS: f32 = 0.01;
+
+/// The area of the current stage, the camera never shows anything outside it.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct StageBounds(pub Rect);
+
+/// Shakes the camera.
+///
+/// Trauma from several events adds up to at most 1, and wears off over time. The shake grows
+/// with the square of the trauma, so small hits barely register and big ones stand out.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ScreenShakeEvent {
+    pub trauma: f32,
+}
+
+#[derive(Resource, Default)]
+struct CameraRig {
+    /// Where the camera follows the players to, before look-ahead and shake.
+    focus: Option<Vec2>,
+    look_ahead: f32,
+    trauma: f32,
+}
+
+/// The fraction of a gap closed over `delta` seconds when closing it at `rate`.
+fn smoothing(rate: f32, delta: f32) -> f32 {
+    1.0 - (-rate * delta).exp()
+}
+
+/// Smooth noise in `[-1, 1]`, a different curve for every `seed`.
+fn wobble(t: f32, seed: f32) -> f32 {
+    ((t + seed).sin() + (t * 2.3 + seed * 1.7).sin() * 0.5) / 1.5
+}
+
+/// Moves `position` so a view `half_size` around it stays inside `bounds`, centering it on
+/// the bounds along any axis where the view doesn't fit.
+fn clamp_to_bounds(position: Vec2, half_size: Vec2, bounds: Rect) -> Vec2 {
+    let min = bounds.min + half_size;
+    let max = bounds.max - half_size;
+    let center = bounds.center();
+    Vec2::new(
+        if min.x <= max.x {
+            position.x.clamp(min.x, max.x)
+        } else {
+            center.x
+        },
+        if min.y <= max.y {
+            position.y.clamp(min.y, max.y)
+        } else {
+            center.y
+        },
+    )
+}
+
+fn reset_camera_rig(mut rig: ResMut<CameraRig>) {
+    *rig = CameraRig::default();
+}
+
+fn add_trauma(
+    mut rig: ResMut<CameraRig>,
+    mut shakes: EventReader<ScreenShakeEvent>,
+    mut explosions: EventReader<ExplosionEvent>,
+) {
+    let trauma = shakes.read().map(|shake| shake.trauma).sum::<f32>()
+        + explosions
+            .read()
+            .map(|explosion| explosion.radius * EXPLOSION_TRAUMA_PER_RADIUS)
+            .sum::<f32>();
+    rig.trauma = (rig.trauma + trauma).clamp(0.0, 1.0);
+}
+
+/// Keeps every living player in frame, zooming out up to [`MAX_CAMERA_ZOOM`] when they spread
+/// out, looking ahead of where they face and staying inside the [`StageBounds`].
+fn camera_follow(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    bounds: Option<Res<StageBounds>>,
+    mut rig: ResMut<CameraRig>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<GameCamera>>,
+    players: Query<(&GlobalTransform, &TextureAtlasSprite, Has<Dead>), With<Player>>,
+) {
+    let Ok((mut camera_transform, mut projection)) = camera.get_single_mut() else {
+        return;
+    };
+    // The size of the view at a scale of 1, which depends on the game resolution.
+    let view = projection.area.size() / projection.scale;
+    if view.min_element() <= 0.0 {
+        return;
+    }
+
+    // Frame the survivors, or everyone once the whole party is dead. Last frame's global
+    // transforms are where the players were drawn, between physics steps.
+    let framed = |(transform, sprite, _): (&GlobalTransform, &TextureAtlasSprite, bool)| {
+        let facing = if sprite.flip_x { -1.0 } else { 1.0 };
+        (transform.translation().truncate(), facing)
+    };
+    let mut framed_players: Vec<(Vec2, f32)> = players
+        .iter()
+        .filter(|(_, _, is_dead)| !is_dead)
+        .map(framed)
+        .collect();
+    if framed_players.is_empty() {
+        framed_players = players.iter().map(framed).collect();
+    }
+    let Some((first, _)) = framed_players.first().copied() else {
+        return;
+    };
+
+    let (min, max) = framed_players
+        .iter()
+        .fold((first, first), |(min, max), (p, _)| {
+            (min.min(*p), max.max(*p))
+        });
+    let center = (min + max) / 2.0;
+    let extents = max - min + Vec2::splat(CAMERA_FRAME_MARGIN * 2.0);
+    let zoom = (extents / view).max_element().clamp(1.0, MAX_CAMERA_ZOOM);
+    let delta = time.delta_seconds();
+    projection.scale += (zoom - projection.scale) * smoothing(ZOOM_RATE, delta);
+
+    // Players facing opposite ways cancel each other out.
+    let facing =
+        framed_players.iter().map(|(_, facing)| facing).sum::<f32>() / framed_players.len() as f32;
+    rig.look_ahead += (facing * LOOK_AHEAD - rig.look_ahead) * smoothing(LOOK_AHEAD_RATE, delta);
+
+    let focus = *rig.focus.get_or_insert(center);
+    let target_y = center.y.clamp(
+        focus.y - DEAD_ZONE_HALF_HEIGHT,
+        focus.y + DEAD_ZONE_HALF_HEIGHT,
+    );
+    let target = Vec2::new(center.x, focus.y + (center.y - target_y));
+    let focus = focus.lerp(target, smoothing(FOLLOW_RATE, delta));
+    rig.focus = Some(focus);
+
+    let mut position = focus + Vec2::X * rig.look_ahead;
+    if let Some(bounds) = bounds {
+        position = clamp_to_bounds(position, view * projection.scale / 2.0, bounds.0);
+    }
+
+    rig.trauma = (rig.trauma - TRAUMA_DECAY * delta).max(0.0);
+    let shake = rig.trauma * rig.trauma * settings.gameplay.screen_shake;
+    let t = time.elapsed_seconds() * SHAKE_FREQUENCY;
+    let offset = Vec2::new(wobble(t, 0.0), wobble(t, 3.1)) * MAX_SHAKE_OFFSET * shake;
+
+    camera_transform.translation = (position + offset).extend(camera_transform.translation.z);
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraRig>()
+            .add_event::<ScreenShakeEvent>()
+            .add_systems(OnEnter(AppState::InGame), reset_camera_rig)
+            .add_systems(
+                PostUpdate,
+                (add_trauma, camera_follow)
+                    .chain()
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_stays_inside_bounds() {
+        let bounds = Rect::new(-100.0, -50.0, 100.0, 50.0);
+        let half_view = Vec2::new(40.0, 30.0);
+
+        assert_eq!(
+            clamp_to_bounds(Vec2::new(90.0, -45.0), half_view, bounds),
+            Vec2::new(60.0, -20.0)
+        );
+        assert_eq!(
+            clamp_to_bounds(Vec2::new(10.0, 5.0), half_view, bounds),
+            Vec2::new(10.0, 5.0)
+        );
+        // Too tall to fit, so centered vertically.
+        assert_eq!(
+            clamp_to_bounds(Vec2::new(10.0, 5.0), Vec2::new(40.0, 80.0), bounds),
+            Vec2::new(10.0, 0.0)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use super::profile::RunStats;
 use crate::assets::GameFont;
 
 use bevy::prelude::*;
@@ -32,11 +33,12 @@ pub fn spawn_clock_text(mut commands: Commands, game_font: Res<GameFont>) {
     ));
 }
 
+/// Shows the run clock, which stops in menus and carries over into a continued run.
 pub fn clock_text_update_system(
-    time: Res<Time>,
+    stats: Res<RunStats>,
     mut clock_text: Query<&mut Text, With<ClockText>>,
 ) {
     for mut text in &mut clock_text {
-        text.sections[0].value = format!("Clock: {:.1?}", time.elapsed()); //time.elapsed().as_secs());
+        text.sections[0].value = format!("Clock: {:.1?}", stats.time.elapsed());
     }
 }
@@ -0,0 +1,398 @@
+use bevy::{ecs::query::Has, prelude::*, utils::HashSet};
+use bevy_xpbd_2d::prelude::*;
+
+use super::items::ItemId;
+use super::net::AuthoritySet;
+use super::physics_layers::Layer;
+
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.0
+    }
+}
+
+/// Absorbs damage before [`Health`]. Recharges fully once the entity goes a while without
+/// being hit.
+#[derive(Component, Reflect)]
+pub struct Shield {
+    pub current: f32,
+    pub max: f32,
+    recharge_delay: Timer,
+}
+
+impl Shield {
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            recharge_delay: Timer::from_seconds(SHIELD_RECHARGE_DELAY, TimerMode::Once),
+        }
+    }
+}
+
+const SHIELD_RECHARGE_DELAY: f32 = 7.0;
+/// Fraction of the maximum shield recharged per second.
+const SHIELD_RECHARGE_RATE: f32 = 0.5;
+
+/// Temporary health on top of [`Health`] and [`Shield`] that is lost first and decays over time.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Barrier(pub f32);
+
+/// Fraction of the maximum health worth of barrier lost per second.
+const BARRIER_DECAY_RATE: f32 = 0.05;
+
+/// Which side a character, attack or projectile is on.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Team {
+    Player,
+    Enemy,
+}
+
+impl Team {
+    pub fn opponent(self) -> Self {
+        match self {
+            Team::Player => Team::Enemy,
+            Team::Enemy => Team::Player,
+        }
+    }
+
+    /// The layer of this team's character bodies.
+    pub fn body_layer(self) -> Layer {
+        match self {
+            Team::Player => Layer::Player,
+            Team::Enemy => Layer::Enemy,
+        }
+    }
+
+    pub fn projectile_layer(self) -> Layer {
+        match self {
+            Team::Player => Layer::PlayerProjectile,
+            Team::Enemy => Layer::EnemyProjectile,
+        }
+    }
+
+    pub fn hitbox_layer(self) -> Layer {
+        match self {
+            Team::Player => Layer::PlayerHitbox,
+            Team::Enemy => Layer::EnemyHitbox,
+        }
+    }
+
+    pub fn hurtbox_layer(self) -> Layer {
+        match self {
+            Team::Player => Layer::PlayerHurtbox,
+            Team::Enemy => Layer::EnemyHurtbox,
+        }
+    }
+}
+
+/// A marker component for entities whose [`Health`] has been depleted.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Dead;
+
+/// Ignores all incoming damage for good, toggled from the dev console.
+#[derive(Component)]
+pub struct GodMode;
+
+/// Grants [`Invulnerable`] for the given number of seconds whenever the entity takes damage.
+#[derive(Component)]
+pub struct IFramesOnHit(pub f32);
+
+/// Ignores all incoming damage until the timer runs out. The sprite flashes in the meantime.
+#[derive(Component, Deref, DerefMut)]
+pub struct Invulnerable(pub Timer);
+
+const IFRAME_FLASH_RATE: f32 = 16.0;
+
+/// The set of items that already proc'd further up a proc chain.
+///
+/// Damage caused by a proc carries the mask of the hit that triggered it plus the item itself,
+/// so an item can never trigger itself again through its own damage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub struct ProcChainMask(u32);
+
+impl ProcChainMask {
+    pub fn contains(self, item: ItemId) -> bool {
+        self.0 & item.bit() != 0
+    }
+
+    pub fn with(self, item: ItemId) -> Self {
+        Self(self.0 | item.bit())
+    }
+}
+
+/// An event sent for every instance of damage dealt to an entity.
+#[derive(Event, Clone, Debug)]
+pub struct DamageEvent {
+    pub attacker: Option<Entity>,
+    pub target: Entity,
+    pub amount: f32,
+    /// Scales the chance of on-hit and on-kill items triggering from this hit.
+    /// `1.0` for regular attacks, `0.0` means the hit can't proc anything.
+    pub proc_coefficient: f32,
+    pub proc_chain: ProcChainMask,
+    /// Velocity the target is launched with, if it is able to be knocked back.
+    pub knockback: Vec2,
+    /// Seconds the target can't act for. Only heavy hits should cause hitstun.
+    pub hitstun: f32,
+    pub crit: bool,
+}
+
+impl DamageEvent {
+    pub fn new(attacker: Option<Entity>, target: Entity, amount: f32) -> Self {
+        Self {
+            attacker,
+            target,
+            amount,
+            proc_coefficient: 1.0,
+            proc_chain: ProcChainMask::default(),
+            knockback: Vec2::ZERO,
+            hitstun: 0.0,
+            crit: false,
+        }
+    }
+
+    pub fn with_proc_coefficient(mut self, proc_coefficient: f32) -> Self {
+        self.proc_coefficient = proc_coefficient;
+        self
+    }
+
+    pub fn with_proc_chain(mut self, proc_chain: ProcChainMask) -> Self {
+        self.proc_chain = proc_chain;
+        self
+    }
+
+    pub fn with_knockback(mut self, knockback: Vec2) -> Self {
+        self.knockback = knockback;
+        self
+    }
+
+    pub fn with_hitstun(mut self, hitstun: f32) -> Self {
+        self.hitstun = hitstun;
+        self
+    }
+
+    pub fn with_crit(mut self, crit: bool) -> Self {
+        self.crit = crit;
+        self
+    }
+}
+
+/// An event sent for every [`DamageEvent`] that actually landed, e.g. wasn't blocked by
+/// [`Invulnerable`]. The amount includes damage absorbed by [`Barrier`] and [`Shield`].
+///
+/// On-hit items roll against these, so blocked hits can't proc anything.
+#[derive(Event, Clone, Debug)]
+pub struct DamageDealtEvent {
+    pub attacker: Option<Entity>,
+    pub target: Entity,
+    pub position: Vec2,
+    pub amount: f32,
+    pub crit: bool,
+    pub proc_coefficient: f32,
+    pub proc_chain: ProcChainMask,
+}
+
+/// An event sent when damage that carries knockback or hitstun lands on a target.
+#[derive(Event, Clone, Debug)]
+pub struct KnockbackEvent {
+    pub target: Entity,
+    pub knockback: Vec2,
+    pub hitstun: f32,
+}
+
+/// An event sent when a [`DamageEvent`] depletes the [`Health`] of its target.
+#[derive(Event, Clone, Debug)]
+pub struct KillEvent {
+    pub attacker: Option<Entity>,
+    pub victim: Entity,
+    pub position: Vec2,
+    pub damage: f32,
+    pub proc_coefficient: f32,
+    pub proc_chain: ProcChainMask,
+}
+
+fn apply_damage(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    mut kill_events: EventWriter<KillEvent>,
+    mut dealt_events: EventWriter<DamageDealtEvent>,
+    mut knockback_events: EventWriter<KnockbackEvent>,
+    mut query: Query<
+        (
+            &mut Health,
+            Option<&mut Shield>,
+            Option<&mut Barrier>,
+            Option<&Position>,
+            Option<&IFramesOnHit>,
+            Has<Invulnerable>,
+        ),
+        (Without<Dead>, Without<GodMode>),
+    >,
+) {
+    // `Invulnerable` is only inserted once the commands are applied, hits later in the same
+    // frame have to be blocked by hand.
+    let mut made_invulnerable = HashSet::new();
+    for event in damage_events.read() {
+        let Ok((mut health, shield, barrier, position, iframes, is_invulnerable)) =
+            query.get_mut(event.target)
+        else {
+            continue;
+        };
+        if is_invulnerable || made_invulnerable.contains(&event.target) {
+            continue;
+        }
+        // Several hits can land on the same frame, only the first one to empty the health bar
+        // counts as the kill.
+        if health.is_dead() {
+            continue;
+        }
+
+        // Barrier soaks up damage first, then shield, and whatever is left goes to health.
+        let mut amount = event.amount;
+        if let Some(mut barrier) = barrier {
+            let absorbed = amount.min(barrier.0);
+            barrier.0 -= absorbed;
+            amount -= absorbed;
+        }
+        if let Some(mut shield) = shield {
+            let absorbed = amount.min(shield.current);
+            shield.current -= absorbed;
+            shield.recharge_delay.reset();
+            amount -= absorbed;
+        }
+        health.current = (health.current - amount).max(0.0);
+
+        dealt_events.send(DamageDealtEvent {
+            attacker: event.attacker,
+            target: event.target,
+            position: position.map_or(Vec2::ZERO, |p| p.0),
+            amount: event.amount,
+            crit: event.crit,
+            proc_coefficient: event.proc_coefficient,
+            proc_chain: event.proc_chain,
+        });
+
+        if event.knockback != Vec2::ZERO || event.hitstun > 0.0 {
+            knockback_events.send(KnockbackEvent {
+                target: event.target,
+                knockback: event.knockback,
+                hitstun: event.hitstun,
+            });
+        }
+
+        if health.is_dead() {
+            commands.entity(event.target).insert(Dead);
+            kill_events.send(KillEvent {
+                attacker: event.attacker,
+                victim: event.target,
+                position: position.map_or(Vec2::ZERO, |p| p.0),
+                damage: event.amount,
+                proc_coefficient: event.proc_coefficient,
+                proc_chain: event.proc_chain,
+            });
+        } else if let Some(iframes) = iframes {
+            made_invulnerable.insert(event.target);
+            commands
+                .entity(event.target)
+                .insert(Invulnerable(Timer::from_seconds(
+                    iframes.0,
+                    TimerMode::Once,
+                )));
+        }
+    }
+}
+
+fn recharge_shields(time: Res<Time>, mut query: Query<&mut Shield, Without<Dead>>) {
+    for mut shield in &mut query {
+        if shield.current >= shield.max {
+            continue;
+        }
+        if shield.recharge_delay.tick(time.delta()).finished() {
+            let recharged =
+                shield.current + shield.max * SHIELD_RECHARGE_RATE * time.delta_seconds();
+            shield.current = recharged.min(shield.max);
+        }
+    }
+}
+
+fn decay_barriers(time: Res<Time>, mut query: Query<(&mut Barrier, &Health)>) {
+    for (mut barrier, health) in &mut query {
+        // Only touch the barrier when there is one, so HUDs can rely on change detection.
+        if barrier.0 > 0.0 {
+            barrier.0 =
+                (barrier.0 - health.max * BARRIER_DECAY_RATE * time.delta_seconds()).max(0.0);
+        }
+    }
+}
+
+/// Flashes invulnerable sprites and removes [`Invulnerable`] once it runs out.
+fn tick_invulnerability(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &mut Invulnerable,
+        Option<&mut Sprite>,
+        Option<&mut TextureAtlasSprite>,
+    )>,
+) {
+    for (entity, mut invulnerable, sprite, atlas_sprite) in &mut query {
+        invulnerable.tick(time.delta());
+
+        let finished = invulnerable.finished();
+        let visible = finished || (invulnerable.elapsed_secs() * IFRAME_FLASH_RATE) as u32 % 2 == 0;
+        let alpha = if visible { 1.0 } else { 0.2 };
+        if let Some(mut sprite) = sprite {
+            sprite.color.set_a(alpha);
+        }
+        if let Some(mut sprite) = atlas_sprite {
+            sprite.color.set_a(alpha);
+        }
+
+        if finished {
+            commands.entity(entity).remove::<Invulnerable>();
+        }
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ApplyDamageSet;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Health>()
+            .register_type::<Shield>()
+            .register_type::<Barrier>()
+            .register_type::<Dead>()
+            .add_event::<DamageEvent>()
+            .add_event::<KillEvent>()
+            .add_event::<DamageDealtEvent>()
+            .add_event::<KnockbackEvent>()
+            .add_systems(
+                Update,
+                (
+                    apply_damage.in_set(ApplyDamageSet),
+                    tick_invulnerability,
+                    recharge_shields.after(ApplyDamageSet),
+                    decay_barriers.after(ApplyDamageSet),
+                )
+                    .in_set(AuthoritySet),
+            );
+    }
+}
@@ -0,0 +1,634 @@
+//! A drop-down developer console, opened with the backtick key.
+//!
+//! Commands are kept in [`ConsoleCommands`] and run with the whole world at hand, type `help`
+//! for the list. Tab completes command names and their first argument, Up and Down go through
+//! the history.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use bevy::{
+    ecs::{query::Has, system::RunSystemOnce},
+    input::InputSystem,
+    prelude::*,
+};
+use bevy_xpbd_2d::prelude::*;
+
+use super::combat::{GodMode, Health};
+use super::enemy::dummy::spawn_temp_dummy;
+use super::enemy::{spawn_enemy, EnemyId};
+use super::hud::HudTarget;
+use super::input::ActionStateSet;
+use super::items::{Inventory, ItemId};
+use super::physics_layers::Layer;
+use super::player::{Player, PlayerXp, LEVEL_UP_XP};
+use super::player_controller::{
+    ControllerGravity, JumpCount, JumpImpulse, MovementAcceleration, Noclip,
+};
+use super::util::RunEntity;
+use crate::{spawn_gravity_zones, spawn_rope, spawn_temp_floor, GameFont, GRAVITY};
+
+const TOGGLE_KEY: KeyCode = KeyCode::Grave;
+const MAX_LOG_LINES: usize = 16;
+const MAX_HISTORY: usize = 50;
+const FONT_SIZE: f32 = 16.0;
+const BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.8);
+const ERROR_PREFIX: &str = "error: ";
+
+/// The stats `set_stat` can change.
+const STATS: [&str; 4] = ["speed", "jump", "jumps", "max_health"];
+/// The stages `stage` can load.
+const STAGES: [&str; 1] = ["test"];
+
+/// What a command prints, or why it failed.
+pub type CommandResult = Result<String, String>;
+
+#[derive(Clone)]
+pub struct ConsoleCommand {
+    pub name: &'static str,
+    /// The arguments as shown by `help`, e.g. `<id> [count]`.
+    pub usage: &'static str,
+    /// The values the first argument is completed from.
+    pub completions: fn() -> Vec<&'static str>,
+    pub run: fn(&mut World, &[&str]) -> CommandResult,
+}
+
+impl ConsoleCommand {
+    pub fn new(
+        name: &'static str,
+        usage: &'static str,
+        run: fn(&mut World, &[&str]) -> CommandResult,
+    ) -> Self {
+        Self {
+            name,
+            usage,
+            completions: Vec::new,
+            run,
+        }
+    }
+
+    pub fn with_completions(mut self, completions: fn() -> Vec<&'static str>) -> Self {
+        self.completions = completions;
+        self
+    }
+}
+
+/// Every command the console knows, other plugins can add their own.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(Vec<ConsoleCommand>);
+
+impl ConsoleCommands {
+    pub fn add(&mut self, command: ConsoleCommand) -> &mut Self {
+        self.0.push(command);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.0.iter().find(|command| command.name == name)
+    }
+
+    /// Completes the word being typed at the end of `input`.
+    ///
+    /// Returns the completed input and every value the word could become. Several candidates
+    /// only complete as far as they agree.
+    fn complete(&self, input: &str) -> (String, Vec<&'static str>) {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let (index, prefix) = match words.last() {
+            Some(word) if !input.ends_with(char::is_whitespace) => (words.len() - 1, *word),
+            _ => (words.len(), ""),
+        };
+
+        let options = match index {
+            0 => self.0.iter().map(|command| command.name).collect(),
+            1 => self
+                .get(words[0])
+                .map_or_else(Vec::new, |command| (command.completions)()),
+            _ => Vec::new(),
+        };
+        let candidates: Vec<&'static str> = options
+            .into_iter()
+            .filter(|option| option.starts_with(prefix))
+            .collect();
+
+        let completed = match candidates.as_slice() {
+            [] => return (input.to_string(), candidates),
+            [only] => format!("{only} "),
+            [first, rest @ ..] => rest.iter().fold(first.to_string(), |common, candidate| {
+                common
+                    .chars()
+                    .zip(candidate.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect()
+            }),
+        };
+        let start = input.len() - prefix.len();
+        (format!("{}{completed}", &input[..start]), candidates)
+    }
+}
+
+#[derive(Resource, Default)]
+struct Console {
+    open: bool,
+    input: String,
+    log: VecDeque<String>,
+    /// Submitted lines, oldest first.
+    history: VecDeque<String>,
+    /// Where Up and Down are in the history, `None` while typing a new line.
+    history_index: Option<usize>,
+    /// Lines submitted this frame, run in `Update`.
+    pending: Vec<String>,
+    /// The candidates of the last ambiguous completion.
+    hint: String,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.log.push_back(line.into());
+        while self.log.len() > MAX_LOG_LINES {
+            self.log.pop_front();
+        }
+    }
+
+    /// Steps through the history, towards older lines if `back`.
+    fn browse_history(&mut self, back: bool) {
+        let index = match (self.history_index, back) {
+            (None, true) => self.history.len().checked_sub(1),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) => Some(index + 1).filter(|index| *index < self.history.len()),
+        };
+        self.history_index = index;
+        self.input = index.map_or_else(String::new, |index| self.history[index].clone());
+    }
+}
+
+#[derive(Component)]
+struct ConsoleRoot;
+
+#[derive(Component)]
+struct ConsoleLogText;
+
+#[derive(Component)]
+struct ConsoleInputText;
+
+fn spawn_console(mut commands: Commands, game_font: Res<GameFont>) {
+    let style = TextStyle {
+        font: game_font.0.clone(),
+        font_size: FONT_SIZE,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            Name::new("Console"),
+            ConsoleRoot,
+            NodeBundle {
+                style: Style {
+                    display: Display::None,
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(8.0)),
+                    row_gap: Val::Px(4.0),
+                    ..default()
+                },
+                background_color: BACKGROUND.into(),
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((ConsoleLogText, TextBundle::from_section("", style.clone())));
+            parent.spawn((
+                ConsoleInputText,
+                TextBundle::from_sections([
+                    TextSection::new("", style.clone()),
+                    TextSection::new(
+                        "",
+                        TextStyle {
+                            color: Color::GRAY,
+                            ..style
+                        },
+                    ),
+                ]),
+            ));
+        });
+}
+
+/// Handles typing into the open console.
+///
+/// Keyboard input is used up while the console is open, so typing doesn't also play the game.
+fn console_input(
+    mut console: ResMut<Console>,
+    mut keys: ResMut<Input<KeyCode>>,
+    mut characters: ResMut<Events<ReceivedCharacter>>,
+    commands: Res<ConsoleCommands>,
+) {
+    if keys.just_pressed(TOGGLE_KEY) {
+        console.open = !console.open;
+    }
+    if !console.open {
+        return;
+    }
+
+    let typed: String = characters
+        .drain()
+        .map(|event| event.char)
+        .filter(|char| !char.is_control() && *char != '`')
+        .collect();
+    if !typed.is_empty() {
+        console.input.push_str(&typed);
+        console.history_index = None;
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        console.input.pop();
+    }
+    if keys.just_pressed(KeyCode::Tab) {
+        let (completed, candidates) = commands.complete(&console.input);
+        console.input = completed;
+        console.hint = if candidates.len() > 1 {
+            candidates.join(" ")
+        } else {
+            String::new()
+        };
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        console.browse_history(true);
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        console.browse_history(false);
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        let line = std::mem::take(&mut console.input).trim().to_string();
+        console.history_index = None;
+        console.hint.clear();
+        if !line.is_empty() {
+            if console.history.back() != Some(&line) {
+                console.history.push_back(line.clone());
+            }
+            while console.history.len() > MAX_HISTORY {
+                console.history.pop_front();
+            }
+            console.pending.push(line);
+        }
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        console.open = false;
+    }
+
+    keys.reset_all();
+}
+
+fn run_console_commands(world: &mut World) {
+    if world.resource::<Console>().pending.is_empty() {
+        return;
+    }
+    let lines = std::mem::take(&mut world.resource_mut::<Console>().pending);
+
+    for line in lines {
+        world.resource_mut::<Console>().print(format!("> {line}"));
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let run = world
+            .resource::<ConsoleCommands>()
+            .get(words[0])
+            .map(|command| command.run);
+        let result = match run {
+            Some(run) => run(world, &words[1..]),
+            None => Err(format!("Unknown command '{}', try help", words[0])),
+        };
+
+        let mut console = world.resource_mut::<Console>();
+        match result {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => output.lines().for_each(|line| console.print(line)),
+            Err(err) => console.print(format!("{ERROR_PREFIX}{err}")),
+        }
+    }
+}
+
+fn update_console_ui(
+    console: Res<Console>,
+    mut roots: Query<&mut Style, With<ConsoleRoot>>,
+    mut log_texts: Query<&mut Text, With<ConsoleLogText>>,
+    mut input_texts: Query<&mut Text, (With<ConsoleInputText>, Without<ConsoleLogText>)>,
+) {
+    for mut style in &mut roots {
+        style.display = if console.open {
+            Display::Flex
+        } else {
+            Display::None
+        };
+    }
+    for mut text in &mut log_texts {
+        text.sections[0].value = console.log.iter().cloned().collect::<Vec<_>>().join("\n");
+    }
+    for mut text in &mut input_texts {
+        text.sections[0].value = format!("> {}_", console.input);
+        text.sections[1].value = if console.hint.is_empty() {
+            String::new()
+        } else {
+            format!("\n{}", console.hint)
+        };
+    }
+}
+
+/// Reads the argument at `index`.
+fn arg<T: FromStr>(args: &[&str], index: usize, name: &str) -> Result<T, String> {
+    let arg = args.get(index).ok_or_else(|| format!("Missing {name}"))?;
+    arg.parse().map_err(|_| format!("Invalid {name} '{arg}'"))
+}
+
+/// Reads the argument at `index`, if it was given.
+fn optional_arg<T: FromStr>(args: &[&str], index: usize, name: &str) -> Result<Option<T>, String> {
+    args.get(index).map(|_| arg(args, index, name)).transpose()
+}
+
+/// The player cheats apply to, the one the HUD follows.
+fn target_player(world: &mut World) -> Result<Entity, String> {
+    world
+        .query_filtered::<(Entity, Has<HudTarget>), With<Player>>()
+        .iter(world)
+        .max_by_key(|(_, is_hud_target)| *is_hud_target)
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| "There's no player, start a run first".to_string())
+}
+
+/// A component of the target player.
+fn player_component<T: Component>(world: &mut World) -> Result<Mut<'_, T>, String> {
+    let player = target_player(world)?;
+    world
+        .get_mut::<T>(player)
+        .ok_or_else(|| "The player can't have that".to_string())
+}
+
+fn help(world: &mut World, _args: &[&str]) -> CommandResult {
+    let commands = &world.resource::<ConsoleCommands>().0;
+    Ok(commands
+        .iter()
+        .map(|command| format!("{} {}", command.name, command.usage))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn clear(world: &mut World, _args: &[&str]) -> CommandResult {
+    world.resource_mut::<Console>().log.clear();
+    Ok(String::new())
+}
+
+fn item_ids() -> Vec<&'static str> {
+    ItemId::ALL.iter().map(|item| item.id()).collect()
+}
+
+fn give_item(world: &mut World, args: &[&str]) -> CommandResult {
+    let id: String = arg(args, 0, "item")?;
+    let item = ItemId::from_id(&id).ok_or_else(|| format!("Unknown item '{id}'"))?;
+    let count = optional_arg(args, 1, "count")?.unwrap_or(1);
+    player_component::<Inventory>(world)?.add(item, count);
+    Ok(format!("Gave {count} {}", item.name()))
+}
+
+fn give_xp(world: &mut World, args: &[&str]) -> CommandResult {
+    let amount = optional_arg(args, 0, "amount")?.unwrap_or(LEVEL_UP_XP);
+    player_component::<PlayerXp>(world)?.0 += amount;
+    Ok(format!("Gave {amount} XP"))
+}
+
+fn enemy_ids() -> Vec<&'static str> {
+    EnemyId::ALL.iter().map(|enemy| enemy.id()).collect()
+}
+
+fn spawn(world: &mut World, args: &[&str]) -> CommandResult {
+    let id: String = arg(args, 0, "enemy")?;
+    let enemy = EnemyId::from_id(&id).ok_or_else(|| format!("Unknown enemy '{id}'"))?;
+    let elite = match args.get(1) {
+        None => false,
+        Some(&"elite") => true,
+        Some(arg) => return Err(format!("Expected 'elite', got '{arg}'")),
+    };
+
+    // A little in front of the player, or in the middle of the stage without one.
+    let position = target_player(world)
+        .ok()
+        .and_then(|player| world.get::<Position>(player))
+        .map_or(Vec2::ZERO, |position| position.0 + Vec2::X * 40.0);
+    world.run_system_once_with(
+        (enemy, position, elite),
+        |In((enemy, position, elite)): In<(EnemyId, Vec2, bool)>,
+         mut commands: Commands,
+         assets: Res<AssetServer>| {
+            spawn_enemy(&mut commands, &assets, enemy, position, elite);
+        },
+    );
+    Ok(format!(
+        "Spawned {}{id}",
+        if elite { "an elite " } else { "a " }
+    ))
+}
+
+fn stat_names() -> Vec<&'static str> {
+    STATS.to_vec()
+}
+
+fn set_stat(world: &mut World, args: &[&str]) -> CommandResult {
+    let stat: String = arg(args, 0, "stat")?;
+    let value: f32 = arg(args, 1, "value")?;
+    match stat.as_str() {
+        "speed" => player_component::<MovementAcceleration>(world)?.0 = value,
+        "jump" => player_component::<JumpImpulse>(world)?.0 = value,
+        "jumps" => player_component::<JumpCount>(world)?.max = value as u32,
+        "max_health" => {
+            let mut health = player_component::<Health>(world)?;
+            health.max = value;
+            health.current = value;
+        }
+        _ => {
+            return Err(format!(
+                "Unknown stat '{stat}', try one of {}",
+                STATS.join(", ")
+            ))
+        }
+    }
+    Ok(format!("Set {stat} to {value}"))
+}
+
+fn god(world: &mut World, _args: &[&str]) -> CommandResult {
+    let player = target_player(world)?;
+    let mut player = world.entity_mut(player);
+    if player.take::<GodMode>().is_some() {
+        return Ok("God mode off".to_string());
+    }
+    player.insert(GodMode);
+    Ok("God mode on".to_string())
+}
+
+fn noclip(world: &mut World, _args: &[&str]) -> CommandResult {
+    let player = target_player(world)?;
+    let mut player = world.entity_mut(player);
+    if let Some(Noclip(layers)) = player.take::<Noclip>() {
+        player.insert(layers);
+        return Ok("Noclip off".to_string());
+    }
+    let layers = player
+        .take::<CollisionLayers>()
+        .ok_or("The player has no collider")?;
+    player.insert((
+        Noclip(layers),
+        CollisionLayers::new::<Layer>([], []),
+        LinearVelocity::ZERO,
+    ));
+    Ok("Noclip on".to_string())
+}
+
+fn tp(world: &mut World, args: &[&str]) -> CommandResult {
+    let target = Vec2::new(arg(args, 0, "x")?, arg(args, 1, "y")?);
+    let player = target_player(world)?;
+    world
+        .entity_mut(player)
+        .insert((Position(target), LinearVelocity::ZERO));
+    Ok(format!("Teleported to {}, {}", target.x, target.y))
+}
+
+fn set_gravity(world: &mut World, args: &[&str]) -> CommandResult {
+    let gravity = match args {
+        [] => GRAVITY,
+        _ => Vec2::new(arg(args, 0, "x")?, arg(args, 1, "y")?),
+    };
+    world.resource_mut::<Gravity>().0 = gravity;
+    for mut controller_gravity in world.query::<&mut ControllerGravity>().iter_mut(world) {
+        controller_gravity.0 = gravity;
+    }
+    Ok(format!("Set gravity to {}, {}", gravity.x, gravity.y))
+}
+
+fn stage_names() -> Vec<&'static str> {
+    STAGES.to_vec()
+}
+
+fn stage(world: &mut World, args: &[&str]) -> CommandResult {
+    let name: String = arg(args, 0, "stage")?;
+    if !STAGES.contains(&name.as_str()) {
+        return Err(format!("Unknown stage '{name}'"));
+    }
+    target_player(world)?;
+
+    // Everything in the run but the players and the HUD.
+    let level: Vec<Entity> = world
+        .query_filtered::<Entity, (With<RunEntity>, Without<Player>, Without<Node>)>()
+        .iter(world)
+        .collect();
+    for entity in level {
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn_recursive();
+        }
+    }
+    world.run_system_once(spawn_temp_floor);
+    world.run_system_once(spawn_temp_dummy);
+    world.run_system_once(spawn_rope);
+    world.run_system_once(spawn_gravity_zones);
+    Ok(format!("Loaded stage {name}"))
+}
+
+fn time_scale(world: &mut World, args: &[&str]) -> CommandResult {
+    let scale: f32 = arg(args, 0, "scale")?;
+    if !scale.is_finite() || scale < 0.0 {
+        return Err("The scale can't be negative".to_string());
+    }
+    world
+        .resource_mut::<Time<Virtual>>()
+        .set_relative_speed(scale);
+    Ok(format!("Time runs at {scale}x"))
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        let mut commands = ConsoleCommands::default();
+        commands
+            .add(ConsoleCommand::new("help", "", help))
+            .add(ConsoleCommand::new("clear", "", clear))
+            .add(
+                ConsoleCommand::new("give_item", "<id> [count]", give_item)
+                    .with_completions(item_ids),
+            )
+            .add(ConsoleCommand::new("give_xp", "[amount]", give_xp))
+            .add(ConsoleCommand::new("spawn", "<enemy> [elite]", spawn).with_completions(enemy_ids))
+            .add(
+                ConsoleCommand::new("set_stat", "<stat> <value>", set_stat)
+                    .with_completions(stat_names),
+            )
+            .add(ConsoleCommand::new("god", "", god))
+            .add(ConsoleCommand::new("noclip", "", noclip))
+            .add(ConsoleCommand::new("tp", "<x> <y>", tp))
+            .add(ConsoleCommand::new("set_gravity", "[<x> <y>]", set_gravity))
+            .add(ConsoleCommand::new("stage", "<name>", stage).with_completions(stage_names))
+            .add(ConsoleCommand::new("time_scale", "<scale>", time_scale));
+
+        app.insert_resource(commands)
+            .init_resource::<Console>()
+            .add_systems(Startup, spawn_console)
+            .add_systems(
+                PreUpdate,
+                console_input.after(InputSystem).before(ActionStateSet),
+            )
+            .add_systems(
+                Update,
+                (
+                    run_console_commands,
+                    update_console_ui.run_if(resource_changed::<Console>()),
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands() -> ConsoleCommands {
+        let mut commands = ConsoleCommands::default();
+        commands
+            .add(ConsoleCommand::new("give_item", "", give_item).with_completions(item_ids))
+            .add(ConsoleCommand::new("give_xp", "", give_xp))
+            .add(ConsoleCommand::new("god", "", god));
+        commands
+    }
+
+    #[test]
+    fn completes_commands_and_arguments() {
+        let commands = commands();
+        assert_eq!(commands.complete("go").0, "god ");
+        assert_eq!(commands.complete("give_item uku").0, "give_item ukulele ");
+        assert_eq!(
+            commands.complete("give_item ukulele 3").0,
+            "give_item ukulele 3"
+        );
+    }
+
+    #[test]
+    fn ambiguous_completions_stop_where_they_differ() {
+        let commands = commands();
+        let (completed, candidates) = commands.complete("gi");
+        assert_eq!(completed, "give_");
+        assert_eq!(candidates, ["give_item", "give_xp"]);
+        assert_eq!(commands.complete("give_item ").1.len(), ItemId::ALL.len());
+    }
+
+    #[test]
+    fn history_goes_both_ways() {
+        let mut console = Console::default();
+        console
+            .history
+            .extend(["god".to_string(), "noclip".to_string()]);
+        console.browse_history(true);
+        assert_eq!(console.input, "noclip");
+        console.browse_history(true);
+        console.browse_history(true);
+        assert_eq!(console.input, "god");
+        console.browse_history(false);
+        console.browse_history(false);
+        assert_eq!(console.input, "");
+    }
+}
@@ -1,23 +1,72 @@
 pub use bevy::prelude::*;
 pub use bevy_xpbd_2d::prelude::*;
 
+pub use crate::game::combat::{Dead, Health, Team};
+pub use crate::game::hitbox::{HurtboxBundle, MeleeAttack, MeleeAttacker};
+pub use crate::game::net::NetId;
 pub use crate::game::physics_layers::Layer;
+pub use crate::game::util::RunEntity;
 
 #[derive(Component)]
 pub struct Dummy;
 
 pub fn spawn_temp_dummy(mut commands: Commands, asset: Res<AssetServer>) {
-    commands.spawn((
-        Name::new("Dummy"),
-        Dummy,
-        SpriteBundle {
-            texture: asset.load("sprites/dummy.png"),
-            transform: Transform::from_xyz(0.0, -215.0, -1.0),
-            ..Default::default()
-        },
-        RigidBody::Static,
-        Collider::cuboid(15.0, 20.0),
-        Friction::new(0.0),
-        CollisionLayers::new([Layer::Enemy], [Layer::Ground]),
-    ));
+    let dummy = spawn_dummy(&mut commands, &asset, Vec2::new(0.0, -215.0));
+    commands.entity(dummy).insert(NetId(NetId::FIRST_LEVEL_ID));
+}
+
+/// The dummy slams the ground around itself when a player gets close.
+fn dummy_slam() -> MeleeAttacker {
+    let attack = MeleeAttack {
+        // The dummy isn't animated, the hitbox stays up for the whole swing.
+        active_frames: 0..=0,
+        offset: Vec2::ZERO,
+        size: Vec2::new(40.0, 24.0),
+        damage: 8.0,
+        proc_coefficient: 1.0,
+        knockback: Vec2::new(150.0, 100.0),
+        hitstun: 0.2,
+        screen_shake: 0.3,
+    };
+    MeleeAttacker::new(attack, 24.0, 0.3, 2.0)
+}
+
+/// Spawns a training dummy standing at `position`.
+pub fn spawn_dummy(commands: &mut Commands, asset: &AssetServer, position: Vec2) -> Entity {
+    commands
+        .spawn((
+            Name::new("Dummy"),
+            Dummy,
+            RunEntity,
+            SpriteBundle {
+                texture: asset.load("sprites/dummy.png"),
+                transform: Transform::from_translation(position.extend(-1.0)),
+                ..Default::default()
+            },
+            RigidBody::Static,
+            Collider::cuboid(15.0, 20.0),
+            Friction::new(0.0),
+            CollisionLayers::new([Layer::Enemy], [Layer::Ground, Layer::PlayerProjectile]),
+            Health::new(500.0),
+            Team::Enemy,
+            dummy_slam(),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Name::new("Hurtbox"),
+                HurtboxBundle::new(Team::Enemy, Collider::cuboid(15.0, 20.0)),
+            ));
+        })
+        .id()
+}
+
+/// Training dummies can't die, they just patch themselves up.
+pub fn reset_dummy_health(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Health), (With<Dummy>, With<Dead>)>,
+) {
+    for (entity, mut health) in &mut query {
+        health.current = health.max;
+        commands.entity(entity).remove::<Dead>();
+    }
 }
@@ -1 +1,95 @@
-pub mod dummy;
+pub mod dummy;
+
+use bevy::prelude::*;
+
+use self::dummy::{reset_dummy_health, spawn_dummy};
+use super::combat::{Health, Team};
+use super::net::AuthoritySet;
+use super::player::PlayerCount;
+
+/// How much more health elites have than the regular kind.
+pub const ELITE_HEALTH_MULTIPLIER: f32 = 4.0;
+const ELITE_TINT: Color = Color::rgb(1.0, 0.55, 0.35);
+/// How much of its base health an enemy gains for each player past the first.
+pub const PARTY_HEALTH_SCALING: f32 = 0.3;
+
+/// Every kind of enemy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EnemyId {
+    Dummy,
+}
+
+impl EnemyId {
+    pub const ALL: [EnemyId; 1] = [EnemyId::Dummy];
+
+    /// The id enemies are referred to by in the dev console.
+    pub fn id(self) -> &'static str {
+        match self {
+            EnemyId::Dummy => "dummy",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|enemy| enemy.id() == id)
+    }
+}
+
+/// An elite enemy, tougher than the regular kind and tinted to stand out.
+#[derive(Component)]
+pub struct Elite;
+
+/// Spawns an enemy standing at `position`.
+pub fn spawn_enemy(
+    commands: &mut Commands,
+    assets: &AssetServer,
+    enemy: EnemyId,
+    position: Vec2,
+    elite: bool,
+) -> Entity {
+    let entity = match enemy {
+        EnemyId::Dummy => spawn_dummy(commands, assets, position),
+    };
+    if elite {
+        commands.entity(entity).insert(Elite);
+    }
+    entity
+}
+
+fn apply_elite_bonus(mut query: Query<(&mut Health, Option<&mut Sprite>), Added<Elite>>) {
+    for (mut health, sprite) in &mut query {
+        health.max *= ELITE_HEALTH_MULTIPLIER;
+        health.current = health.max;
+        if let Some(mut sprite) = sprite {
+            sprite.color = ELITE_TINT;
+        }
+    }
+}
+
+/// Scales new enemies' health to the size of the party.
+fn scale_to_player_count(
+    player_count: Res<PlayerCount>,
+    mut query: Query<(&Team, &mut Health), Added<Health>>,
+) {
+    let multiplier = 1.0 + PARTY_HEALTH_SCALING * player_count.0.saturating_sub(1) as f32;
+    for (team, mut health) in &mut query {
+        if *team == Team::Enemy {
+            health.max *= multiplier;
+            health.current = health.max;
+        }
+    }
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                (scale_to_player_count, apply_elite_bonus).chain(),
+                reset_dummy_health,
+            )
+                .in_set(AuthoritySet),
+        );
+    }
+}
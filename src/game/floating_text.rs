@@ -0,0 +1,373 @@
+use bevy::{prelude::*, transform::TransformSystem};
+
+use super::combat::{ApplyDamageSet, DamageDealtEvent};
+use super::player_controller::InterpolateSet;
+use super::settings::Settings;
+use super::util::RunEntity;
+use crate::{GameFont, TEXT_SCALE};
+
+/// Floating text is drawn above everything in the world.
+const FLOATING_TEXT_Z: f32 = 10.0;
+/// The last part of a floating text's lifetime it spends fading out, as a fraction.
+const FADE_FRACTION: f32 = 0.5;
+
+const DAMAGE_NUMBER_SIZE: f32 = 4.0;
+const DAMAGE_NUMBER_LIFETIME: f32 = 0.8;
+const DAMAGE_NUMBER_DRIFT: f32 = 12.0;
+const DAMAGE_NUMBER_OFFSET: f32 = 8.0;
+/// Hits landing on a target within this many seconds of each other share one number.
+const DAMAGE_NUMBER_MERGE_WINDOW: f32 = 0.25;
+const DAMAGE_COLOR: Color = Color::WHITE;
+const CRIT_COLOR: Color = Color::rgb(1.0, 0.3, 0.2);
+
+/// World-space text that drifts, fades out and despawns once its lifetime is over.
+#[derive(Component)]
+pub struct FloatingText {
+    /// An entity to stay attached to, the text stays where it is once it's gone.
+    pub follow: Option<Entity>,
+    pub anchor: Vec2,
+    pub offset: Vec2,
+    /// Velocity of the offset, in pixels per second.
+    pub velocity: Vec2,
+    pub lifetime: Timer,
+}
+
+#[derive(Bundle)]
+pub struct FloatingTextBundle {
+    floating_text: FloatingText,
+    text: Text2dBundle,
+    run_entity: RunEntity,
+}
+
+impl FloatingTextBundle {
+    /// Creates floating text `size` game pixels tall.
+    ///
+    /// The font is rendered [`TEXT_SCALE`] times larger and scaled back down, so the text
+    /// stays crisp in the low resolution world.
+    pub fn new(
+        value: impl Into<String>,
+        size: f32,
+        color: Color,
+        game_font: &GameFont,
+        position: Vec2,
+    ) -> Self {
+        let style = TextStyle {
+            font: game_font.0.clone(),
+            font_size: size * TEXT_SCALE,
+            color,
+        };
+
+        Self {
+            floating_text: FloatingText {
+                follow: None,
+                anchor: position,
+                offset: Vec2::ZERO,
+                velocity: Vec2::ZERO,
+                lifetime: Timer::from_seconds(1.0, TimerMode::Once),
+            },
+            text: Text2dBundle {
+                text: Text::from_section(value, style).with_alignment(TextAlignment::Center),
+                transform: Transform::from_translation(position.extend(FLOATING_TEXT_Z))
+                    .with_scale(Vec3::new(1.0 / TEXT_SCALE, 1.0 / TEXT_SCALE, 1.0)),
+                ..default()
+            },
+            run_entity: RunEntity,
+        }
+    }
+
+    pub fn following(mut self, entity: Entity, offset: Vec2) -> Self {
+        self.floating_text.follow = Some(entity);
+        self.floating_text.offset = offset;
+        self.text.transform.translation =
+            (self.floating_text.anchor + offset).extend(FLOATING_TEXT_Z);
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec2) -> Self {
+        self.floating_text.velocity = velocity;
+        self
+    }
+
+    pub fn with_lifetime(mut self, seconds: f32) -> Self {
+        self.floating_text.lifetime = Timer::from_seconds(seconds, TimerMode::Once);
+        self
+    }
+}
+
+/// The damage a [`FloatingText`] is showing, so quick hits on the same target add up.
+#[derive(Component)]
+struct DamageNumber {
+    target: Entity,
+    amount: f32,
+    crit: bool,
+}
+
+fn damage_text(amount: f32, crit: bool) -> String {
+    if crit {
+        format!("{:.0}!", amount.ceil())
+    } else {
+        format!("{:.0}", amount.ceil())
+    }
+}
+
+fn spawn_damage_numbers(
+    mut commands: Commands,
+    game_font: Res<GameFont>,
+    settings: Res<Settings>,
+    mut damage_events: EventReader<DamageDealtEvent>,
+    mut numbers: Query<(&mut DamageNumber, &mut FloatingText, &mut Text)>,
+) {
+    if !settings.gameplay.damage_numbers {
+        damage_events.clear();
+        return;
+    }
+
+    // Numbers spawned this frame aren't in the query yet, merge hits within the frame here.
+    let mut spawned: Vec<(DamageNumber, Vec2)> = Vec::new();
+
+    for event in damage_events.read() {
+        let existing = numbers.iter_mut().find(|(number, floating_text, _)| {
+            number.target == event.target
+                && floating_text.lifetime.elapsed_secs() < DAMAGE_NUMBER_MERGE_WINDOW
+        });
+
+        if let Some((mut number, mut floating_text, mut text)) = existing {
+            number.amount += event.amount;
+            number.crit |= event.crit;
+            floating_text.lifetime.reset();
+            text.sections[0].value = damage_text(number.amount, number.crit);
+            text.sections[0].style.color = if number.crit {
+                CRIT_COLOR
+            } else {
+                DAMAGE_COLOR
+            };
+        } else if let Some((number, _)) = spawned.iter_mut().find(|(n, _)| n.target == event.target)
+        {
+            number.amount += event.amount;
+            number.crit |= event.crit;
+        } else {
+            let number = DamageNumber {
+                target: event.target,
+                amount: event.amount,
+                crit: event.crit,
+            };
+            spawned.push((number, event.position));
+        }
+    }
+
+    for (number, position) in spawned {
+        let (color, size) = if number.crit {
+            (CRIT_COLOR, DAMAGE_NUMBER_SIZE * 1.5)
+        } else {
+            (DAMAGE_COLOR, DAMAGE_NUMBER_SIZE)
+        };
+        commands.spawn((
+            FloatingTextBundle::new(
+                damage_text(number.amount, number.crit),
+                size,
+                color,
+                &game_font,
+                position,
+            )
+            .following(number.target, Vec2::Y * DAMAGE_NUMBER_OFFSET)
+            .with_velocity(Vec2::Y * DAMAGE_NUMBER_DRIFT)
+            .with_lifetime(DAMAGE_NUMBER_LIFETIME),
+            number,
+        ));
+    }
+}
+
+/// Moves floating text along with the entity it follows and drifts it by its velocity.
+///
+/// Follows where the entity is drawn rather than its physics position, so the text doesn't
+/// jitter against an interpolated body. That is only known after transform propagation, so the
+/// text's own [`GlobalTransform`] is set here too.
+fn move_floating_text(
+    time: Res<Time>,
+    mut texts: Query<(&mut FloatingText, &mut Transform, &mut GlobalTransform)>,
+    targets: Query<&GlobalTransform, Without<FloatingText>>,
+) {
+    for (mut floating_text, mut transform, mut global_transform) in &mut texts {
+        if let Some(target) = floating_text.follow.and_then(|e| targets.get(e).ok()) {
+            floating_text.anchor = target.translation().truncate();
+        }
+        let velocity = floating_text.velocity;
+        floating_text.offset += velocity * time.delta_seconds();
+
+        let translation = floating_text.anchor + floating_text.offset;
+        transform.translation = translation.extend(FLOATING_TEXT_Z);
+        *global_transform = GlobalTransform::from(*transform);
+    }
+}
+
+fn fade_floating_text(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Text)>,
+) {
+    for (entity, mut floating_text, mut text) in &mut texts {
+        if floating_text.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let alpha = (floating_text.lifetime.percent_left() / FADE_FRACTION).min(1.0);
+        for section in &mut text.sections {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_damage_numbers.after(ApplyDamageSet),
+                fade_floating_text,
+            ),
+        )
+        .add_systems(
+            PostUpdate,
+            move_floating_text
+                .after(TransformSystem::TransformPropagate)
+                .after(InterpolateSet),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    const FRAME_SECS: f32 = 0.05;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, FloatingTextPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+                FRAME_SECS,
+            )))
+            .insert_resource(Settings::default())
+            .insert_resource(GameFont(Handle::default()))
+            .add_event::<DamageDealtEvent>();
+        // The first update only starts the clock.
+        app.update();
+        app
+    }
+
+    fn hit(app: &mut App, target: Entity, amount: f32, crit: bool) {
+        app.world.send_event(DamageDealtEvent {
+            attacker: None,
+            target,
+            position: Vec2::ZERO,
+            amount,
+            crit,
+            proc_coefficient: 1.0,
+            proc_chain: default(),
+        });
+    }
+
+    /// The text of every damage number.
+    fn numbers(app: &mut App) -> Vec<Text> {
+        app.world
+            .query_filtered::<&Text, With<DamageNumber>>()
+            .iter(&app.world)
+            .cloned()
+            .collect()
+    }
+
+    fn rgb(color: Color) -> [f32; 3] {
+        let [r, g, b, _] = color.as_rgba_f32();
+        [r, g, b]
+    }
+
+    #[test]
+    fn quick_hits_on_a_target_share_one_number() {
+        let mut app = app();
+        let target = app.world.spawn(TransformBundle::default()).id();
+        let other = app.world.spawn(TransformBundle::default()).id();
+
+        hit(&mut app, target, 5.0, false);
+        hit(&mut app, target, 7.0, false);
+        app.update();
+        let texts = numbers(&mut app);
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].sections[0].value, "12");
+
+        // Still within the merge window on the next frame, but not for other targets.
+        hit(&mut app, target, 3.0, false);
+        hit(&mut app, other, 4.0, false);
+        app.update();
+        let mut values: Vec<String> = numbers(&mut app)
+            .iter()
+            .map(|text| text.sections[0].value.clone())
+            .collect();
+        values.sort();
+        assert_eq!(values, ["15", "4"]);
+    }
+
+    #[test]
+    fn crits_are_coloured_and_marked() {
+        let mut app = app();
+        let target = app.world.spawn(TransformBundle::default()).id();
+
+        hit(&mut app, target, 10.0, false);
+        app.update();
+        let text = &numbers(&mut app)[0].sections[0];
+        assert_eq!(text.value, "10");
+        assert_eq!(rgb(text.style.color), rgb(DAMAGE_COLOR));
+
+        // A crit merged into the number turns all of it into a crit.
+        hit(&mut app, target, 20.0, true);
+        app.update();
+        let text = &numbers(&mut app)[0].sections[0];
+        assert_eq!(text.value, "30!");
+        assert_eq!(rgb(text.style.color), rgb(CRIT_COLOR));
+    }
+
+    #[test]
+    fn numbers_fade_out_and_despawn() {
+        let mut app = app();
+        let target = app.world.spawn(TransformBundle::default()).id();
+        hit(&mut app, target, 10.0, false);
+        app.update();
+        assert_eq!(numbers(&mut app)[0].sections[0].style.color.a(), 1.0);
+
+        let frames = (DAMAGE_NUMBER_LIFETIME / FRAME_SECS).round() as usize;
+        for _ in 0..frames * 3 / 4 {
+            app.update();
+        }
+        let alpha = numbers(&mut app)[0].sections[0].style.color.a();
+        assert!(alpha > 0.0 && alpha < 1.0, "{alpha}");
+
+        for _ in 0..frames / 4 + 1 {
+            app.update();
+        }
+        assert!(numbers(&mut app).is_empty());
+    }
+
+    #[test]
+    fn numbers_follow_where_the_target_is_drawn() {
+        let mut app = app();
+        let target = app.world.spawn(TransformBundle::default()).id();
+        hit(&mut app, target, 10.0, false);
+        app.update();
+
+        app.world.get_mut::<Transform>(target).unwrap().translation = Vec3::new(30.0, 40.0, 0.0);
+        app.update();
+        let (floating_text, global_transform) = app
+            .world
+            .query::<(&FloatingText, &GlobalTransform)>()
+            .single(&app.world);
+        let expected = Vec2::new(30.0, 40.0) + floating_text.offset;
+        assert_eq!(global_transform.translation().truncate(), expected);
+        assert!(floating_text.offset.y > DAMAGE_NUMBER_OFFSET);
+    }
+}
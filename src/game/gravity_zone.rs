@@ -0,0 +1,98 @@
+//! Volumes that change the gravity of the character controllers inside them.
+//!
+//! A zone is a sensor on [`Layer::GravityZone`], the controllers look up the zones they overlap
+//! every fixed step, see [`EffectiveGravity`](super::player_controller::EffectiveGravity).
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::{math::*, prelude::*};
+
+use super::physics_layers::Layer;
+
+/// How a zone changes the gravity of the controllers inside it.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum GravityZone {
+    /// Multiplies gravity, below 1 for low gravity.
+    Scale(Scalar),
+    /// Replaces gravity, e.g. pointing up for an anti-gravity lift.
+    Set(Vector),
+    /// Adds to gravity, e.g. pushing sideways for wind.
+    Add(Vector),
+}
+
+impl GravityZone {
+    /// The gravity of a controller with `base` gravity inside all of `zones`.
+    ///
+    /// Overlapping zones don't depend on their order: replacements are averaged, then scaled,
+    /// then added to.
+    pub fn combine(base: Vector, zones: impl IntoIterator<Item = GravityZone>) -> Vector {
+        let mut replaced = Vector::ZERO;
+        let mut replacements = 0;
+        let mut scale = 1.0;
+        let mut added = Vector::ZERO;
+        for zone in zones {
+            match zone {
+                GravityZone::Scale(factor) => scale *= factor,
+                GravityZone::Set(gravity) => {
+                    replaced += gravity;
+                    replacements += 1;
+                }
+                GravityZone::Add(gravity) => added += gravity,
+            }
+        }
+
+        let gravity = if replacements > 0 {
+            replaced / replacements as Scalar
+        } else {
+            base
+        };
+        gravity * scale + added
+    }
+}
+
+/// A gravity zone `size` across, place it with a `Transform`.
+#[derive(Bundle)]
+pub struct GravityZoneBundle {
+    zone: GravityZone,
+    sensor: Sensor,
+    collider: Collider,
+    col_layers: CollisionLayers,
+}
+
+impl GravityZoneBundle {
+    pub fn new(zone: GravityZone, size: Vector) -> Self {
+        Self {
+            zone,
+            sensor: Sensor,
+            collider: Collider::cuboid(size.x, size.y),
+            col_layers: CollisionLayers::new([Layer::GravityZone], []),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: Vector = Vector::new(0.0, -1000.0);
+
+    #[test]
+    fn no_zones_keep_the_base_gravity() {
+        assert_eq!(GravityZone::combine(BASE, []), BASE);
+    }
+
+    #[test]
+    fn zones_combine_in_any_order() {
+        let zones = [
+            GravityZone::Add(Vector::new(200.0, 0.0)),
+            GravityZone::Scale(0.5),
+            GravityZone::Set(Vector::new(0.0, 400.0)),
+            GravityZone::Set(Vector::new(0.0, 800.0)),
+        ];
+        let expected = Vector::new(200.0, 300.0);
+        assert_eq!(GravityZone::combine(BASE, zones), expected);
+
+        let mut reversed = zones;
+        reversed.reverse();
+        assert_eq!(GravityZone::combine(BASE, reversed), expected);
+    }
+}
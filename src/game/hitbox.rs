@@ -0,0 +1,275 @@
+use std::ops::RangeInclusive;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use super::camera::ScreenShakeEvent;
+use super::combat::{DamageEvent, Dead, Team};
+use super::net::AuthoritySet;
+
+/// The sensor volume that can be hit by the opposing team's hitboxes.
+///
+/// Spawned as a child of the character it belongs to.
+#[derive(Component)]
+pub struct Hurtbox;
+
+#[derive(Bundle)]
+pub struct HurtboxBundle {
+    hurtbox: Hurtbox,
+    collider: Collider,
+    sensor: Sensor,
+    col_layers: CollisionLayers,
+    transform: TransformBundle,
+}
+
+impl HurtboxBundle {
+    pub fn new(team: Team, collider: Collider) -> Self {
+        Self {
+            hurtbox: Hurtbox,
+            collider,
+            sensor: Sensor,
+            col_layers: CollisionLayers::new(
+                [team.hurtbox_layer()],
+                [team.opponent().hitbox_layer()],
+            ),
+            transform: TransformBundle::default(),
+        }
+    }
+}
+
+/// The short-lived attack volume of a [`MeleeSwing`], spawned as a child of the attacker.
+#[derive(Component)]
+pub struct Hitbox;
+
+/// Describes a melee attack and the animation frames it is able to hit on.
+#[derive(Clone, Debug)]
+pub struct MeleeAttack {
+    /// Sprite indices of the attacker during which the hitbox is live.
+    pub active_frames: RangeInclusive<usize>,
+    /// Offset of the hitbox from the attacker when facing right, mirrored when facing left.
+    pub offset: Vec2,
+    pub size: Vec2,
+    pub damage: f32,
+    pub proc_coefficient: f32,
+    /// Knockback when hitting a target to the right, mirrored for targets to the left.
+    pub knockback: Vec2,
+    pub hitstun: f32,
+    /// Trauma the camera takes when the attack hits a player.
+    pub screen_shake: f32,
+}
+
+/// A melee attack in progress. Insert it to start a swing, remove it once the animation is over.
+///
+/// Every target can only be hit once per swing, no matter how long it stays inside the hitbox.
+#[derive(Component)]
+pub struct MeleeSwing {
+    pub attack: MeleeAttack,
+    hitbox: Option<Entity>,
+    already_hit: Vec<Entity>,
+}
+
+impl MeleeSwing {
+    pub fn new(attack: MeleeAttack) -> Self {
+        Self {
+            attack,
+            hitbox: None,
+            already_hit: Vec::new(),
+        }
+    }
+
+    pub fn already_hit(&self) -> &[Entity] {
+        &self.already_hit
+    }
+}
+
+/// Swings a [`MeleeAttack`] whenever an opponent comes within `range`, for attackers without an
+/// animation to time the swing by.
+#[derive(Component, Clone, Debug)]
+pub struct MeleeAttacker {
+    pub attack: MeleeAttack,
+    pub range: f32,
+    /// How long the hitbox stays up.
+    swing: Timer,
+    /// The time between the starts of two swings.
+    cooldown: Timer,
+}
+
+impl MeleeAttacker {
+    pub fn new(attack: MeleeAttack, range: f32, swing_secs: f32, cooldown_secs: f32) -> Self {
+        let mut cooldown = Timer::from_seconds(cooldown_secs, TimerMode::Once);
+        // Ready to swing from the start.
+        cooldown.tick(cooldown.duration());
+        Self {
+            attack,
+            range,
+            swing: Timer::from_seconds(swing_secs, TimerMode::Once),
+            cooldown,
+        }
+    }
+}
+
+/// Starts and ends the swings of [`MeleeAttacker`]s.
+fn melee_attackers(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut attackers: Query<(
+        Entity,
+        &Team,
+        &GlobalTransform,
+        &mut MeleeAttacker,
+        Has<MeleeSwing>,
+    )>,
+    targets: Query<(&Team, &GlobalTransform), Without<Dead>>,
+) {
+    for (entity, team, transform, mut attacker, swinging) in &mut attackers {
+        attacker.cooldown.tick(time.delta());
+
+        if swinging {
+            if attacker.swing.tick(time.delta()).finished() {
+                commands.entity(entity).remove::<MeleeSwing>();
+            }
+            continue;
+        }
+
+        let position = transform.translation().truncate();
+        let in_range = targets.iter().any(|(target_team, target)| {
+            *target_team == team.opponent()
+                && target.translation().truncate().distance(position) <= attacker.range
+        });
+        if in_range && attacker.cooldown.finished() {
+            attacker.swing.reset();
+            attacker.cooldown.reset();
+            commands
+                .entity(entity)
+                .insert(MeleeSwing::new(attacker.attack.clone()));
+        }
+    }
+}
+
+fn hitbox_offset(attack: &MeleeAttack, facing_left: bool) -> Vec3 {
+    let x = if facing_left {
+        -attack.offset.x
+    } else {
+        attack.offset.x
+    };
+    Vec3::new(x, attack.offset.y, 0.0)
+}
+
+/// Spawns and despawns hitboxes following the attacker's current animation frame.
+#[allow(clippy::type_complexity)]
+fn update_hitboxes(
+    mut commands: Commands,
+    mut swings: Query<(Entity, &Team, &mut MeleeSwing, Option<&TextureAtlasSprite>)>,
+    mut hitboxes: Query<(Entity, &Parent, &mut Transform), With<Hitbox>>,
+) {
+    for (entity, team, mut swing, sprite) in &mut swings {
+        // Characters without an animated sprite keep their hitbox up for the whole swing.
+        let active = sprite.map_or(true, |sprite| {
+            swing.attack.active_frames.contains(&sprite.index)
+        });
+        let facing_left = sprite.is_some_and(|sprite| sprite.flip_x);
+        let offset = hitbox_offset(&swing.attack, facing_left);
+
+        match (active, swing.hitbox) {
+            (true, None) => {
+                let hitbox = commands
+                    .spawn((
+                        Name::new("Hitbox"),
+                        Hitbox,
+                        Collider::cuboid(swing.attack.size.x, swing.attack.size.y),
+                        Sensor,
+                        CollisionLayers::new(
+                            [team.hitbox_layer()],
+                            [team.opponent().hurtbox_layer()],
+                        ),
+                        CollidingEntities::default(),
+                        TransformBundle::from_transform(Transform::from_translation(offset)),
+                    ))
+                    .id();
+                commands.entity(entity).add_child(hitbox);
+                swing.hitbox = Some(hitbox);
+            }
+            (true, Some(hitbox)) => {
+                if let Ok((_, _, mut transform)) = hitboxes.get_mut(hitbox) {
+                    transform.translation = offset;
+                }
+            }
+            // Despawned below along with the hitboxes of finished swings.
+            (false, Some(_)) => swing.hitbox = None,
+            (false, None) => {}
+        }
+    }
+
+    // Clean up hitboxes of swings that ended or were replaced by a new swing.
+    for (hitbox, parent, _) in &hitboxes {
+        let is_current = swings
+            .get(parent.get())
+            .is_ok_and(|(_, _, swing, _)| swing.hitbox == Some(hitbox));
+        if !is_current {
+            commands.entity(hitbox).despawn_recursive();
+        }
+    }
+}
+
+fn register_melee_hits(
+    mut damage_events: EventWriter<DamageEvent>,
+    mut shakes: EventWriter<ScreenShakeEvent>,
+    hitboxes: Query<(&Parent, &CollidingEntities), With<Hitbox>>,
+    hurtboxes: Query<&Parent, With<Hurtbox>>,
+    mut swings: Query<&mut MeleeSwing>,
+    transforms: Query<&GlobalTransform>,
+    teams: Query<&Team>,
+) {
+    for (attacker, colliding) in &hitboxes {
+        let attacker = attacker.get();
+        let Ok(mut swing) = swings.get_mut(attacker) else {
+            continue;
+        };
+
+        let mut targets: Vec<Entity> = colliding
+            .iter()
+            .filter_map(|hurtbox| hurtboxes.get(*hurtbox).ok())
+            .map(|target| target.get())
+            .filter(|target| *target != attacker && !swing.already_hit.contains(target))
+            .collect();
+        // Collisions come out of a hash set, keep the damage order stable.
+        targets.sort();
+        targets.dedup();
+
+        for target in targets {
+            swing.already_hit.push(target);
+
+            let mut knockback = swing.attack.knockback;
+            if let Ok([from, to]) = transforms.get_many([attacker, target]) {
+                if to.translation().x < from.translation().x {
+                    knockback.x = -knockback.x;
+                }
+            }
+
+            damage_events.send(
+                DamageEvent::new(Some(attacker), target, swing.attack.damage)
+                    .with_proc_coefficient(swing.attack.proc_coefficient)
+                    .with_knockback(knockback)
+                    .with_hitstun(swing.attack.hitstun),
+            );
+            if swing.attack.screen_shake > 0.0 && teams.get(target) == Ok(&Team::Player) {
+                shakes.send(ScreenShakeEvent {
+                    trauma: swing.attack.screen_shake,
+                });
+            }
+        }
+    }
+}
+
+pub struct HitboxPlugin;
+
+impl Plugin for HitboxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (melee_attackers, update_hitboxes, register_melee_hits)
+                .chain()
+                .in_set(AuthoritySet),
+        );
+    }
+}
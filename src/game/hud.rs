@@ -0,0 +1,498 @@
+use bevy::prelude::*;
+
+use super::combat::{Barrier, Health, Shield};
+use super::items::Inventory;
+use super::player::{Gold, PlayerLevel, PlayerXp, LEVEL_UP_XP};
+use super::skills::{SkillSlot, Skills};
+use super::util::RunEntity;
+use crate::{AppState, GameFont};
+
+const BAR_WIDTH: f32 = 300.0;
+const BAR_HEIGHT: f32 = 20.0;
+const SKILL_ICON_SIZE: f32 = 48.0;
+const ITEM_ICON_SIZE: f32 = 32.0;
+
+const HEALTH_COLOR: Color = Color::rgb(0.45, 0.8, 0.25);
+const SHIELD_COLOR: Color = Color::rgb(0.3, 0.55, 0.95);
+const BARRIER_COLOR: Color = Color::rgba(0.95, 0.8, 0.2, 0.6);
+const XP_COLOR: Color = Color::rgb(0.6, 0.35, 0.9);
+const BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+
+/// The player whose stats the HUD shows.
+#[derive(Component)]
+pub struct HudTarget;
+
+#[derive(Component)]
+struct HealthFill;
+
+#[derive(Component)]
+struct ShieldFill;
+
+#[derive(Component)]
+struct BarrierFill;
+
+#[derive(Component)]
+struct HealthText;
+
+#[derive(Component)]
+struct XpFill;
+
+#[derive(Component)]
+struct LevelText;
+
+#[derive(Component)]
+struct GoldText;
+
+#[derive(Component)]
+struct CooldownOverlay(SkillSlot);
+
+#[derive(Component)]
+struct CooldownText(SkillSlot);
+
+#[derive(Component)]
+struct ChargeText(SkillSlot);
+
+#[derive(Component)]
+struct ItemStrip;
+
+fn text_style(game_font: &GameFont, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: game_font.0.clone(),
+        font_size,
+        color: Color::WHITE,
+    }
+}
+
+fn bar(width: f32, height: f32) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Px(width),
+            height: Val::Px(height),
+            ..default()
+        },
+        background_color: BAR_BACKGROUND.into(),
+        ..default()
+    }
+}
+
+fn fill(color: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(0.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        background_color: color.into(),
+        ..default()
+    }
+}
+
+/// A node covering its parent that centers its children on top of the parent's other children.
+fn overlay() -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    }
+}
+
+/// A short label for items until they have icons, e.g. "SB" for Sticky Bomb.
+fn item_initials(name: &str) -> String {
+    name.split_whitespace()
+        .filter_map(|word| word.chars().next())
+        .filter(|c| c.is_alphabetic())
+        .take(2)
+        .collect()
+}
+
+fn spawn_hud(mut commands: Commands, game_font: Res<GameFont>) {
+    commands
+        .spawn((
+            Name::new("Hud"),
+            RunEntity,
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::all(Val::Px(12.0)),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            // Top: gold on the left, collected items across the middle.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(20.0)),
+                        column_gap: Val::Px(12.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        GoldText,
+                        TextBundle::from_section("$0", {
+                            let mut style = text_style(&game_font, 16.0);
+                            style.color = Color::GOLD;
+                            style
+                        }),
+                    ));
+                    parent.spawn((
+                        ItemStrip,
+                        NodeBundle {
+                            style: Style {
+                                flex_wrap: FlexWrap::Wrap,
+                                column_gap: Val::Px(4.0),
+                                row_gap: Val::Px(4.0),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ));
+                });
+
+            // Bottom: health and XP on the left, skills on the right.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::FlexEnd,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                row_gap: Val::Px(4.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            spawn_xp_bar(parent, &game_font);
+                            spawn_health_bar(parent, &game_font);
+                        });
+
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                column_gap: Val::Px(6.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for slot in SkillSlot::ALL {
+                                spawn_skill_icon(parent, &game_font, slot);
+                            }
+                        });
+                });
+        });
+}
+
+fn spawn_xp_bar(parent: &mut ChildBuilder, game_font: &GameFont) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(6.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                LevelText,
+                TextBundle::from_section("Lv 1", text_style(game_font, 16.0)),
+            ));
+            parent
+                .spawn(bar(BAR_WIDTH, BAR_HEIGHT / 3.0))
+                .with_children(|parent| {
+                    parent.spawn((XpFill, fill(XP_COLOR)));
+                });
+        });
+}
+
+fn spawn_health_bar(parent: &mut ChildBuilder, game_font: &GameFont) {
+    parent
+        .spawn(bar(BAR_WIDTH, BAR_HEIGHT))
+        .with_children(|parent| {
+            // Health and shield share the bar proportionally, barrier is drawn over both.
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((HealthFill, fill(HEALTH_COLOR)));
+                    parent.spawn((ShieldFill, fill(SHIELD_COLOR)));
+                });
+            parent.spawn((BarrierFill, {
+                let mut barrier = fill(BARRIER_COLOR);
+                barrier.style.position_type = PositionType::Absolute;
+                barrier.style.left = Val::Px(0.0);
+                barrier
+            }));
+            parent.spawn(overlay()).with_children(|parent| {
+                parent.spawn((
+                    HealthText,
+                    TextBundle::from_section("", text_style(game_font, 16.0)),
+                ));
+            });
+        });
+}
+
+fn spawn_skill_icon(parent: &mut ChildBuilder, game_font: &GameFont, slot: SkillSlot) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(SKILL_ICON_SIZE),
+                height: Val::Px(SKILL_ICON_SIZE),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                border: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+            border_color: Color::GRAY.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            // The sweep shrinks from the top as the cooldown runs out.
+            parent.spawn((
+                CooldownOverlay(slot),
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        bottom: Val::Px(0.0),
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(0.0),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+                    ..default()
+                },
+            ));
+            parent.spawn(overlay()).with_children(|parent| {
+                parent.spawn((
+                    CooldownText(slot),
+                    TextBundle::from_section("", text_style(game_font, 16.0)),
+                ));
+            });
+            parent.spawn((
+                ChargeText(slot),
+                TextBundle::from_section("", text_style(game_font, 8.0)).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(2.0),
+                    bottom: Val::Px(2.0),
+                    ..default()
+                }),
+            ));
+        });
+}
+
+#[allow(clippy::type_complexity)]
+fn update_health_bar(
+    players: Query<
+        (&Health, Option<&Shield>, Option<&Barrier>),
+        (
+            With<HudTarget>,
+            Or<(Changed<Health>, Changed<Shield>, Changed<Barrier>)>,
+        ),
+    >,
+    mut fills: ParamSet<(
+        Query<&mut Style, With<HealthFill>>,
+        Query<&mut Style, With<ShieldFill>>,
+        Query<&mut Style, With<BarrierFill>>,
+    )>,
+    mut texts: Query<&mut Text, With<HealthText>>,
+) {
+    let Some((health, shield, barrier)) = players.iter().next() else {
+        return;
+    };
+
+    let (shield_current, shield_max) = shield.map_or((0.0, 0.0), |s| (s.current, s.max));
+    let barrier = barrier.map_or(0.0, |b| b.0);
+    let total = (health.max + shield_max).max(1.0);
+
+    for mut style in &mut fills.p0() {
+        style.width = Val::Percent(health.current / total * 100.0);
+    }
+    for mut style in &mut fills.p1() {
+        style.width = Val::Percent(shield_current / total * 100.0);
+    }
+    for mut style in &mut fills.p2() {
+        style.width = Val::Percent((barrier / total * 100.0).min(100.0));
+    }
+    for mut text in &mut texts {
+        text.sections[0].value = format!(
+            "{:.0} / {:.0}",
+            (health.current + shield_current + barrier).ceil(),
+            health.max + shield_max
+        );
+    }
+}
+
+fn update_xp_bar(
+    players: Query<
+        (&PlayerXp, &PlayerLevel),
+        (
+            With<HudTarget>,
+            Or<(Changed<PlayerXp>, Changed<PlayerLevel>)>,
+        ),
+    >,
+    mut fills: Query<&mut Style, With<XpFill>>,
+    mut texts: Query<&mut Text, With<LevelText>>,
+) {
+    let Some((xp, level)) = players.iter().next() else {
+        return;
+    };
+
+    for mut style in &mut fills {
+        let progress = (xp.0 as f32 / LEVEL_UP_XP as f32).clamp(0.0, 1.0);
+        style.width = Val::Percent(progress * 100.0);
+    }
+    for mut text in &mut texts {
+        // Levels are counted from zero internally.
+        text.sections[0].value = format!("Lv {}", level.0 + 1);
+    }
+}
+
+fn update_gold(
+    players: Query<&Gold, (With<HudTarget>, Changed<Gold>)>,
+    mut texts: Query<&mut Text, With<GoldText>>,
+) {
+    let Some(gold) = players.iter().next() else {
+        return;
+    };
+    for mut text in &mut texts {
+        text.sections[0].value = format!("${}", gold.0);
+    }
+}
+
+fn update_skill_icons(
+    players: Query<&Skills, (With<HudTarget>, Changed<Skills>)>,
+    mut overlays: Query<(&CooldownOverlay, &mut Style)>,
+    mut cooldown_texts: Query<(&CooldownText, &mut Text), Without<ChargeText>>,
+    mut charge_texts: Query<(&ChargeText, &mut Text), Without<CooldownText>>,
+) {
+    let Some(skills) = players.iter().next() else {
+        return;
+    };
+
+    for (overlay, mut style) in &mut overlays {
+        let skill = skills.get(overlay.0);
+        // Only darken the whole icon while there are no charges left to use.
+        let fraction = if skill.is_ready() {
+            0.0
+        } else {
+            skill.cooldown_fraction()
+        };
+        style.height = Val::Percent(fraction * 100.0);
+    }
+    for (cooldown_text, mut text) in &mut cooldown_texts {
+        let skill = skills.get(cooldown_text.0);
+        text.sections[0].value = if skill.is_ready() {
+            String::new()
+        } else {
+            format!("{:.0}", skill.cooldown_remaining().ceil())
+        };
+    }
+    for (charge_text, mut text) in &mut charge_texts {
+        let skill = skills.get(charge_text.0);
+        text.sections[0].value = if skill.max_charges > 1 {
+            skill.charges.to_string()
+        } else {
+            String::new()
+        };
+    }
+}
+
+fn update_item_strip(
+    mut commands: Commands,
+    game_font: Res<GameFont>,
+    players: Query<&Inventory, (With<HudTarget>, Changed<Inventory>)>,
+    strips: Query<Entity, With<ItemStrip>>,
+) {
+    let Some(inventory) = players.iter().next() else {
+        return;
+    };
+
+    for strip in &strips {
+        commands.entity(strip).despawn_descendants();
+        commands.entity(strip).with_children(|parent| {
+            for stack in inventory.stacks() {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(ITEM_ICON_SIZE),
+                            height: Val::Px(ITEM_ICON_SIZE),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            item_initials(stack.item.name()),
+                            text_style(&game_font, 16.0),
+                        ));
+                        if stack.count > 1 {
+                            parent.spawn(
+                                TextBundle::from_section(
+                                    format!("x{}", stack.count),
+                                    text_style(&game_font, 8.0),
+                                )
+                                .with_style(Style {
+                                    position_type: PositionType::Absolute,
+                                    right: Val::Px(1.0),
+                                    bottom: Val::Px(1.0),
+                                    ..default()
+                                }),
+                            );
+                        }
+                    });
+            }
+        });
+    }
+}
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), spawn_hud)
+            .add_systems(
+                Update,
+                (
+                    update_health_bar,
+                    update_xp_bar,
+                    update_gold,
+                    update_skill_icons,
+                    update_item_strip,
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
@@ -0,0 +1,593 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    input::{gamepad::GamepadConnectionEvent, InputSystem},
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use super::player::PlayerIndex;
+use super::settings::Settings;
+
+/// Everything the player can do, independent of the device it's done with.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect, Serialize, Deserialize,
+)]
+pub enum Action {
+    Move,
+    Jump,
+    Climb,
+    Primary,
+    Secondary,
+    Utility,
+    Special,
+    Interact,
+    Pause,
+    Debug,
+}
+
+impl Action {
+    pub const COUNT: usize = 10;
+
+    pub const ALL: [Action; Action::COUNT] = [
+        Action::Move,
+        Action::Jump,
+        Action::Climb,
+        Action::Primary,
+        Action::Secondary,
+        Action::Utility,
+        Action::Special,
+        Action::Interact,
+        Action::Pause,
+        Action::Debug,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Move => "Move",
+            Action::Jump => "Jump",
+            Action::Climb => "Climb",
+            Action::Primary => "Primary",
+            Action::Secondary => "Secondary",
+            Action::Utility => "Utility",
+            Action::Special => "Special",
+            Action::Interact => "Interact",
+            Action::Pause => "Pause",
+            Action::Debug => "Debug",
+        }
+    }
+
+    /// Axis actions have a value between -1 and 1 and are bound in both directions.
+    pub fn is_axis(self) -> bool {
+        matches!(self, Action::Move | Action::Climb)
+    }
+}
+
+/// A single physical input that can trigger an [`Action`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    GamepadButton(GamepadButtonType),
+    /// One direction of a gamepad axis.
+    GamepadAxis {
+        axis: GamepadAxisType,
+        positive: bool,
+    },
+}
+
+impl InputBinding {
+    pub fn is_gamepad(self) -> bool {
+        matches!(
+            self,
+            InputBinding::GamepadButton(_) | InputBinding::GamepadAxis { .. }
+        )
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            InputBinding::Key(key) => format!("{key:?}"),
+            InputBinding::Mouse(button) => format!("Mouse {button:?}"),
+            InputBinding::GamepadButton(button) => format!("Pad {button:?}"),
+            InputBinding::GamepadAxis { axis, positive } => {
+                format!("Pad {axis:?}{}", if positive { "+" } else { "-" })
+            }
+        }
+    }
+}
+
+/// The bindings of one [`Action`]. Button actions only use `positive`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ActionBindings {
+    pub positive: Vec<InputBinding>,
+    pub negative: Vec<InputBinding>,
+}
+
+impl ActionBindings {
+    fn button(bindings: impl Into<Vec<InputBinding>>) -> Self {
+        Self {
+            positive: bindings.into(),
+            negative: Vec::new(),
+        }
+    }
+
+    fn axis(
+        positive: impl Into<Vec<InputBinding>>,
+        negative: impl Into<Vec<InputBinding>>,
+    ) -> Self {
+        Self {
+            positive: positive.into(),
+            negative: negative.into(),
+        }
+    }
+}
+
+/// Maps physical inputs to [`Action`]s, part of the [`Settings`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputMap {
+    pub bindings: BTreeMap<Action, ActionBindings>,
+    /// Stick values closer to the center than this are ignored.
+    pub stick_deadzone: f32,
+    /// Trigger values below this are ignored. Triggers bound as buttons are read as axes too.
+    pub trigger_deadzone: f32,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use GamepadAxisType::*;
+        use GamepadButtonType::*;
+        use InputBinding::{GamepadAxis, GamepadButton, Key, Mouse};
+
+        let bindings = BTreeMap::from([
+            (
+                Action::Move,
+                ActionBindings::axis(
+                    [
+                        Key(KeyCode::D),
+                        Key(KeyCode::Right),
+                        GamepadButton(DPadRight),
+                        GamepadAxis {
+                            axis: LeftStickX,
+                            positive: true,
+                        },
+                    ],
+                    [
+                        Key(KeyCode::A),
+                        Key(KeyCode::Left),
+                        GamepadButton(DPadLeft),
+                        GamepadAxis {
+                            axis: LeftStickX,
+                            positive: false,
+                        },
+                    ],
+                ),
+            ),
+            (
+                Action::Jump,
+                ActionBindings::button([Key(KeyCode::Space), GamepadButton(South)]),
+            ),
+            (
+                Action::Climb,
+                ActionBindings::axis(
+                    [
+                        Key(KeyCode::W),
+                        Key(KeyCode::Up),
+                        GamepadButton(DPadUp),
+                        GamepadAxis {
+                            axis: LeftStickY,
+                            positive: true,
+                        },
+                    ],
+                    [
+                        Key(KeyCode::S),
+                        Key(KeyCode::Down),
+                        GamepadButton(DPadDown),
+                        GamepadAxis {
+                            axis: LeftStickY,
+                            positive: false,
+                        },
+                    ],
+                ),
+            ),
+            (
+                Action::Primary,
+                ActionBindings::button([Mouse(MouseButton::Left), GamepadButton(RightTrigger2)]),
+            ),
+            (
+                Action::Secondary,
+                ActionBindings::button([Mouse(MouseButton::Right), GamepadButton(LeftTrigger2)]),
+            ),
+            (
+                Action::Utility,
+                ActionBindings::button([Key(KeyCode::ShiftLeft), GamepadButton(East)]),
+            ),
+            (
+                Action::Special,
+                ActionBindings::button([Key(KeyCode::Q), GamepadButton(North)]),
+            ),
+            (
+                Action::Interact,
+                ActionBindings::button([Key(KeyCode::E), GamepadButton(West)]),
+            ),
+            (
+                Action::Pause,
+                ActionBindings::button([Key(KeyCode::Escape), GamepadButton(Start)]),
+            ),
+            (
+                Action::Debug,
+                ActionBindings::button([Key(KeyCode::F3), GamepadButton(Select)]),
+            ),
+        ]);
+
+        Self {
+            bindings,
+            stick_deadzone: 0.2,
+            trigger_deadzone: 0.1,
+        }
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> Option<&ActionBindings> {
+        self.bindings.get(&action)
+    }
+
+    /// Replaces the binding of the same device kind (keyboard and mouse, or gamepad) in one
+    /// direction of an action, so rebinding a key keeps the gamepad binding and vice versa.
+    pub fn rebind(&mut self, action: Action, positive: bool, binding: InputBinding) {
+        let bindings = self.bindings.entry(action).or_default();
+        let list = if positive {
+            &mut bindings.positive
+        } else {
+            &mut bindings.negative
+        };
+
+        if let Some(existing) = list
+            .iter_mut()
+            .find(|existing| existing.is_gamepad() == binding.is_gamepad())
+        {
+            *existing = binding;
+        } else {
+            list.push(binding);
+        }
+    }
+
+    fn deadzone(&self, axis: GamepadAxisType) -> f32 {
+        match axis {
+            GamepadAxisType::LeftZ | GamepadAxisType::RightZ => self.trigger_deadzone,
+            _ => self.stick_deadzone,
+        }
+    }
+}
+
+/// Removes the deadzone from an axis value and rescales the rest back to the full range.
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        0.0
+    } else {
+        value.signum() * (value.abs() - deadzone) / (1.0 - deadzone).max(f32::EPSILON)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct ActionValue {
+    value: f32,
+    pressed: bool,
+    just_pressed: bool,
+    just_released: bool,
+}
+
+/// The state of every [`Action`] this frame, updated in [`PreUpdate`] from the [`InputMap`].
+///
+/// The resource combines every device and is meant for menus and debug keys. Players have their
+/// own component that only reads from their [`InputDevices`].
+#[derive(Resource, Component, Default)]
+pub struct ActionState {
+    actions: [ActionValue; Action::COUNT],
+}
+
+impl ActionState {
+    /// The axis value of an action, `0.0` or `1.0` for button actions.
+    pub fn value(&self, action: Action) -> f32 {
+        self.actions[action as usize].value
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.actions[action as usize].pressed
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.actions[action as usize].just_pressed
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.actions[action as usize].just_released
+    }
+
+    pub fn set(&mut self, action: Action, value: f32) {
+        let state = &mut self.actions[action as usize];
+        let pressed = value != 0.0;
+        state.just_pressed = pressed && !state.pressed;
+        state.just_released = !pressed && state.pressed;
+        state.pressed = pressed;
+        state.value = value;
+    }
+}
+
+/// The devices a player is controlled with.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputDevices {
+    pub keyboard_mouse: bool,
+    pub gamepad: Option<Gamepad>,
+}
+
+/// Marks a player whose [`ActionState`] is written by the network instead of local devices.
+#[derive(Component)]
+pub struct RemoteInput;
+
+/// The set in which every [`ActionState`] is updated for the frame.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ActionStateSet;
+
+/// The raw input resources bindings are read from.
+#[derive(bevy::ecs::system::SystemParam)]
+pub struct RawInputs<'w> {
+    pub keys: Res<'w, Input<KeyCode>>,
+    pub mouse: Res<'w, Input<MouseButton>>,
+    pub gamepads: Res<'w, Gamepads>,
+    pub gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    /// How far the analog buttons, i.e. the triggers, are pressed.
+    pub gamepad_button_axes: Res<'w, Axis<GamepadButton>>,
+    pub gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+}
+
+fn is_trigger(button_type: GamepadButtonType) -> bool {
+    matches!(
+        button_type,
+        GamepadButtonType::LeftTrigger2 | GamepadButtonType::RightTrigger2
+    )
+}
+
+impl RawInputs<'_> {
+    /// How strongly a binding is held, from `0.0` to `1.0`, across the given devices.
+    fn binding_value(
+        &self,
+        binding: InputBinding,
+        input_map: &InputMap,
+        keyboard_mouse: bool,
+        gamepads: &[Gamepad],
+    ) -> f32 {
+        let pressed = |pressed: bool| if pressed { 1.0 } else { 0.0 };
+
+        match binding {
+            InputBinding::Key(key) => pressed(keyboard_mouse && self.keys.pressed(key)),
+            InputBinding::Mouse(button) => pressed(keyboard_mouse && self.mouse.pressed(button)),
+            InputBinding::GamepadButton(button_type) if is_trigger(button_type) => gamepads
+                .iter()
+                .filter_map(|&gamepad| {
+                    self.gamepad_button_axes.get(GamepadButton {
+                        gamepad,
+                        button_type,
+                    })
+                })
+                .map(|value| apply_deadzone(value, input_map.trigger_deadzone))
+                .fold(0.0, f32::max),
+            InputBinding::GamepadButton(button_type) => pressed(gamepads.iter().any(|&gamepad| {
+                self.gamepad_buttons.pressed(GamepadButton {
+                    gamepad,
+                    button_type,
+                })
+            })),
+            InputBinding::GamepadAxis { axis, positive } => gamepads
+                .iter()
+                .filter_map(|&gamepad| {
+                    self.gamepad_axes.get(GamepadAxis {
+                        gamepad,
+                        axis_type: axis,
+                    })
+                })
+                .map(|value| apply_deadzone(value, input_map.deadzone(axis)))
+                .map(|value| if positive { value } else { -value }.max(0.0))
+                .fold(0.0, f32::max),
+        }
+    }
+
+    /// Computes the value of every action from the given devices.
+    fn update(
+        &self,
+        action_state: &mut ActionState,
+        input_map: &InputMap,
+        keyboard_mouse: bool,
+        gamepads: &[Gamepad],
+    ) {
+        // The strongest of the given bindings.
+        let bindings_value = |bindings: &[InputBinding]| {
+            bindings
+                .iter()
+                .map(|binding| self.binding_value(*binding, input_map, keyboard_mouse, gamepads))
+                .fold(0.0, f32::max)
+        };
+
+        for action in Action::ALL {
+            let Some(bindings) = input_map.bindings(action) else {
+                action_state.set(action, 0.0);
+                continue;
+            };
+
+            let positive = bindings_value(&bindings.positive);
+            let negative = bindings_value(&bindings.negative);
+            let value = if action.is_axis() {
+                (positive - negative).clamp(-1.0, 1.0)
+            } else {
+                positive
+            };
+            action_state.set(action, value);
+        }
+    }
+
+    /// The first binding pressed this frame, used when rebinding an action.
+    pub fn just_pressed_binding(&self) -> Option<InputBinding> {
+        if let Some(key) = self.keys.get_just_pressed().next() {
+            return Some(InputBinding::Key(*key));
+        }
+        if let Some(button) = self.mouse.get_just_pressed().next() {
+            return Some(InputBinding::Mouse(*button));
+        }
+        self.gamepad_buttons
+            .get_just_pressed()
+            .next()
+            .map(|button| InputBinding::GamepadButton(button.button_type))
+    }
+}
+
+pub(crate) fn update_action_state(
+    inputs: RawInputs,
+    settings: Res<Settings>,
+    mut action_state: ResMut<ActionState>,
+    mut players: Query<(&InputDevices, &mut ActionState), Without<RemoteInput>>,
+) {
+    let input_map = &settings.input;
+    let all_gamepads: Vec<Gamepad> = inputs.gamepads.iter().collect();
+    inputs.update(&mut action_state, input_map, true, &all_gamepads);
+
+    for (devices, mut action_state) in &mut players {
+        let gamepads: Vec<Gamepad> = devices
+            .gamepad
+            .filter(|gamepad| inputs.gamepads.contains(*gamepad))
+            .into_iter()
+            .collect();
+        inputs.update(
+            &mut action_state,
+            input_map,
+            devices.keyboard_mouse,
+            &gamepads,
+        );
+    }
+}
+
+/// Gives newly connected gamepads to the first player without one, and takes them back when
+/// they disconnect. Players without the keyboard get a gamepad first.
+fn assign_gamepads(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    gamepads: Res<Gamepads>,
+    mut players: Query<(&mut InputDevices, &PlayerIndex), Without<RemoteInput>>,
+) {
+    for event in connection_events.read() {
+        if event.disconnected() {
+            for (mut devices, _) in &mut players {
+                if devices.gamepad == Some(event.gamepad) {
+                    devices.gamepad = None;
+                }
+            }
+        }
+    }
+
+    // Also covers pads that were connected before the players spawned.
+    for gamepad in gamepads.iter() {
+        if players
+            .iter()
+            .any(|(devices, _)| devices.gamepad == Some(gamepad))
+        {
+            continue;
+        }
+        let Some((mut devices, index)) = players
+            .iter_mut()
+            .filter(|(devices, _)| devices.gamepad.is_none())
+            .min_by_key(|(devices, index)| (devices.keyboard_mouse, index.0))
+        else {
+            break;
+        };
+        info!("Assigned gamepad {} to player {}", gamepad.id, index.0 + 1);
+        devices.gamepad = Some(gamepad);
+    }
+}
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>().add_systems(
+            PreUpdate,
+            (assign_gamepads, update_action_state)
+                .chain()
+                .in_set(ActionStateSet)
+                .after(InputSystem),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{
+        gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadInfo},
+        InputPlugin,
+    };
+
+    use super::*;
+
+    #[test]
+    fn deadzone_is_removed_and_rescaled() {
+        assert_eq!(apply_deadzone(0.1, 0.2), 0.0);
+        assert_eq!(apply_deadzone(-0.2, 0.2), 0.0);
+        assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+        assert!((apply_deadzone(-1.0, 0.2) + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn triggers_use_the_trigger_deadzone() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, InputMapPlugin))
+            .insert_resource(Settings::default());
+        let gamepad = Gamepad::new(0);
+        app.world.send_event(GamepadConnectionEvent::new(
+            gamepad,
+            GamepadConnection::Connected(GamepadInfo {
+                name: "Pad".to_string(),
+            }),
+        ));
+        app.update();
+
+        // Set the way the gamepad backend does.
+        let mut pull_trigger = |value| {
+            app.world.resource_mut::<Axis<GamepadButton>>().set(
+                GamepadButton::new(gamepad, GamepadButtonType::RightTrigger2),
+                value,
+            );
+            app.update();
+            app.world.resource::<ActionState>().value(Action::Primary)
+        };
+        let deadzone = InputMap::default().trigger_deadzone;
+        assert_eq!(pull_trigger(deadzone / 2.0), 0.0);
+        // Well short of a full press of the button.
+        assert!((pull_trigger(0.55) - apply_deadzone(0.55, deadzone)).abs() < 1e-6);
+        assert_eq!(pull_trigger(1.0), 1.0);
+    }
+
+    #[test]
+    fn rebinding_keeps_other_device() {
+        let mut input_map = InputMap::default();
+        input_map.rebind(Action::Jump, true, InputBinding::Key(KeyCode::K));
+
+        let jump = &input_map.bindings(Action::Jump).unwrap().positive;
+        assert_eq!(
+            jump,
+            &[
+                InputBinding::Key(KeyCode::K),
+                InputBinding::GamepadButton(GamepadButtonType::South)
+            ]
+        );
+    }
+
+    #[test]
+    fn input_map_round_trips_through_ron() {
+        let input_map = InputMap::default();
+        let ron = ron::to_string(&input_map).unwrap();
+        let loaded: InputMap = ron::from_str(&ron).unwrap();
+        assert_eq!(
+            loaded.bindings(Action::Move).unwrap().negative,
+            input_map.bindings(Action::Move).unwrap().negative
+        );
+        assert_eq!(loaded.stick_deadzone, input_map.stick_deadzone);
+    }
+}
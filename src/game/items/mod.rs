@@ -0,0 +1,176 @@
+pub mod procs;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use self::procs::*;
+use super::combat::ApplyDamageSet;
+use super::net::AuthoritySet;
+use super::projectile::ProjectileSet;
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Reflect, Serialize, Deserialize,
+)]
+pub enum ItemId {
+    AtgMissile,
+    Ukulele,
+    StickyBomb,
+    Gasoline,
+}
+
+impl ItemId {
+    pub const ALL: [ItemId; 4] = [
+        ItemId::AtgMissile,
+        ItemId::Ukulele,
+        ItemId::StickyBomb,
+        ItemId::Gasoline,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemId::AtgMissile => "AtG Missile Mk. 1",
+            ItemId::Ukulele => "Ukulele",
+            ItemId::StickyBomb => "Sticky Bomb",
+            ItemId::Gasoline => "Gasoline",
+        }
+    }
+
+    /// The id items are referred to by in the dev console.
+    pub fn id(self) -> &'static str {
+        match self {
+            ItemId::AtgMissile => "atg_missile",
+            ItemId::Ukulele => "ukulele",
+            ItemId::StickyBomb => "sticky_bomb",
+            ItemId::Gasoline => "gasoline",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|item| item.id() == id)
+    }
+
+    /// The bit used for this item in a [`ProcChainMask`](super::combat::ProcChainMask).
+    pub fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: ItemId,
+    pub count: u32,
+}
+
+/// The items an entity has collected, in pickup order.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Inventory {
+    stacks: Vec<ItemStack>,
+}
+
+impl Inventory {
+    pub fn add(&mut self, item: ItemId, count: u32) {
+        if let Some(stack) = self.stacks.iter_mut().find(|stack| stack.item == item) {
+            stack.count += count;
+        } else {
+            self.stacks.push(ItemStack { item, count });
+        }
+    }
+
+    pub fn count(&self, item: ItemId) -> u32 {
+        self.stacks
+            .iter()
+            .find(|stack| stack.item == item)
+            .map_or(0, |stack| stack.count)
+    }
+
+    pub fn stacks(&self) -> &[ItemStack] {
+        &self.stacks
+    }
+}
+
+impl From<Vec<ItemStack>> for Inventory {
+    fn from(stacks: Vec<ItemStack>) -> Self {
+        Self { stacks }
+    }
+}
+
+/// AtG Missile Mk. 1: 10% chance per stack to fire a missile that deals 300% damage.
+fn atg_missile(ctx: &ProcContext) -> ProcAction {
+    ProcAction::Missile {
+        attacker: ctx.attacker,
+        target: ctx.target,
+        amount: ctx.damage * 3.0,
+        proc_coefficient: 1.0,
+        proc_chain: ctx.proc_chain,
+    }
+}
+
+/// Ukulele: 25% chance per stack to fire chain lightning that arcs to 3 (+2 per stack) enemies.
+fn ukulele(ctx: &ProcContext) -> ProcAction {
+    ProcAction::ChainLightning {
+        attacker: ctx.attacker,
+        origin: ctx.target,
+        amount: ctx.damage * 0.8,
+        bounces: 1 + 2 * ctx.stack,
+        radius: 40.0,
+        proc_coefficient: 0.2,
+        proc_chain: ctx.proc_chain,
+    }
+}
+
+/// Sticky Bomb: 5% chance per stack to attach a bomb that explodes for 180% damage.
+fn sticky_bomb(ctx: &ProcContext) -> ProcAction {
+    ProcAction::StickyBomb {
+        attacker: ctx.attacker,
+        target: ctx.target,
+        amount: ctx.damage * 1.8,
+        delay: 1.5,
+        proc_chain: ctx.proc_chain,
+    }
+}
+
+/// Gasoline: killing an enemy ignites the ground, dealing 150% damage per stack around it.
+fn gasoline(ctx: &KillContext) -> ProcAction {
+    ProcAction::Explosion {
+        attacker: ctx.attacker,
+        position: ctx.position,
+        radius: 12.0 + 4.0 * ctx.stack as f32,
+        amount: ctx.damage * 1.5 * ctx.stack as f32,
+        proc_coefficient: 0.0,
+        proc_chain: ctx.proc_chain,
+    }
+}
+
+pub struct ItemsPlugin;
+
+impl Plugin for ItemsPlugin {
+    fn build(&self, app: &mut App) {
+        let mut registry = ProcRegistry::default();
+        registry
+            .register_on_hit(ItemId::AtgMissile, 0.1, atg_missile)
+            .register_on_hit(ItemId::Ukulele, 0.25, ukulele)
+            .register_on_hit(ItemId::StickyBomb, 0.05, sticky_bomb)
+            .register_on_kill(ItemId::Gasoline, 1.0, gasoline);
+
+        app.register_type::<Inventory>()
+            .register_type::<Vec<ItemStack>>()
+            .register_type::<ItemStack>()
+            .register_type::<ItemId>()
+            .insert_resource(registry)
+            .add_event::<ProcAction>()
+            .add_systems(
+                Update,
+                (
+                    roll_on_hit_procs,
+                    roll_on_kill_procs,
+                    execute_proc_actions,
+                    detonate_sticky_bombs,
+                )
+                    .chain()
+                    .after(ApplyDamageSet)
+                    .before(ProjectileSet)
+                    .in_set(AuthoritySet),
+            );
+    }
+}
@@ -0,0 +1,654 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use super::{Inventory, ItemId};
+use crate::engine::rng::Rng;
+use crate::game::combat::{DamageDealtEvent, DamageEvent, Health, KillEvent, ProcChainMask, Team};
+use crate::game::physics_layers::Layer;
+use crate::game::projectile::{ExplosionEvent, Homing, ProjectileBundle};
+use crate::game::run_rng::{RngStream, RunRng};
+use crate::game::util::RunEntity;
+
+/// What an item knows about the hit that triggered it.
+pub struct ProcContext {
+    pub attacker: Entity,
+    pub target: Entity,
+    pub damage: f32,
+    pub stack: u32,
+    /// The chain mask of the triggering hit, including the item that proc'd.
+    pub proc_chain: ProcChainMask,
+}
+
+/// What an item knows about the kill that triggered it.
+pub struct KillContext {
+    pub attacker: Entity,
+    pub victim: Entity,
+    pub position: Vec2,
+    pub damage: f32,
+    pub stack: u32,
+    /// The chain mask of the killing blow, including the item that proc'd.
+    pub proc_chain: ProcChainMask,
+}
+
+/// An effect produced by a successful proc roll.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub enum ProcAction {
+    Damage {
+        attacker: Entity,
+        target: Entity,
+        amount: f32,
+        proc_coefficient: f32,
+        proc_chain: ProcChainMask,
+    },
+    Missile {
+        attacker: Entity,
+        target: Entity,
+        amount: f32,
+        proc_coefficient: f32,
+        proc_chain: ProcChainMask,
+    },
+    ChainLightning {
+        attacker: Entity,
+        origin: Entity,
+        amount: f32,
+        bounces: u32,
+        radius: f32,
+        proc_coefficient: f32,
+        proc_chain: ProcChainMask,
+    },
+    StickyBomb {
+        attacker: Entity,
+        target: Entity,
+        amount: f32,
+        delay: f32,
+        proc_chain: ProcChainMask,
+    },
+    Explosion {
+        attacker: Entity,
+        position: Vec2,
+        radius: f32,
+        amount: f32,
+        proc_coefficient: f32,
+        proc_chain: ProcChainMask,
+    },
+}
+
+pub struct OnHitProc {
+    pub item: ItemId,
+    /// The chance to trigger per stack, before the proc coefficient is applied.
+    pub chance: f32,
+    pub effect: fn(&ProcContext) -> ProcAction,
+}
+
+pub struct OnKillProc {
+    pub item: ItemId,
+    /// The chance to trigger per stack, before the proc coefficient is applied.
+    pub chance: f32,
+    pub effect: fn(&KillContext) -> ProcAction,
+}
+
+/// The on-hit and on-kill handlers of every item, rolled in registration order.
+#[derive(Resource, Default)]
+pub struct ProcRegistry {
+    on_hit: Vec<OnHitProc>,
+    on_kill: Vec<OnKillProc>,
+}
+
+/// The chance of an item with `stack` copies triggering from a hit with the given proc coefficient.
+pub fn proc_chance(chance: f32, stack: u32, proc_coefficient: f32) -> f32 {
+    (chance * stack as f32 * proc_coefficient).clamp(0.0, 1.0)
+}
+
+impl ProcRegistry {
+    pub fn register_on_hit(
+        &mut self,
+        item: ItemId,
+        chance: f32,
+        effect: fn(&ProcContext) -> ProcAction,
+    ) -> &mut Self {
+        self.on_hit.push(OnHitProc {
+            item,
+            chance,
+            effect,
+        });
+        self
+    }
+
+    pub fn register_on_kill(
+        &mut self,
+        item: ItemId,
+        chance: f32,
+        effect: fn(&KillContext) -> ProcAction,
+    ) -> &mut Self {
+        self.on_kill.push(OnKillProc {
+            item,
+            chance,
+            effect,
+        });
+        self
+    }
+
+    /// Rolls every on-hit item the attacker holds against a hit that landed.
+    ///
+    /// Items already present in the hit's [`ProcChainMask`] are skipped without consuming a roll.
+    pub fn roll_on_hit(
+        &self,
+        inventory: &Inventory,
+        attacker: Entity,
+        hit: &DamageDealtEvent,
+        rng: &mut Rng,
+    ) -> Vec<ProcAction> {
+        let mut actions = Vec::new();
+        for handler in &self.on_hit {
+            let stack = inventory.count(handler.item);
+            if stack == 0 || hit.proc_chain.contains(handler.item) {
+                continue;
+            }
+
+            if rng.chance(proc_chance(handler.chance, stack, hit.proc_coefficient)) {
+                actions.push((handler.effect)(&ProcContext {
+                    attacker,
+                    target: hit.target,
+                    damage: hit.amount,
+                    stack,
+                    proc_chain: hit.proc_chain.with(handler.item),
+                }));
+            }
+        }
+        actions
+    }
+
+    /// Rolls every on-kill item the attacker holds against a kill.
+    pub fn roll_on_kill(
+        &self,
+        inventory: &Inventory,
+        attacker: Entity,
+        kill: &KillEvent,
+        rng: &mut Rng,
+    ) -> Vec<ProcAction> {
+        let mut actions = Vec::new();
+        for handler in &self.on_kill {
+            let stack = inventory.count(handler.item);
+            if stack == 0 || kill.proc_chain.contains(handler.item) {
+                continue;
+            }
+
+            if rng.chance(proc_chance(handler.chance, stack, kill.proc_coefficient)) {
+                actions.push((handler.effect)(&KillContext {
+                    attacker,
+                    victim: kill.victim,
+                    position: kill.position,
+                    damage: kill.damage,
+                    stack,
+                    proc_chain: kill.proc_chain.with(handler.item),
+                }));
+            }
+        }
+        actions
+    }
+}
+
+pub(super) fn roll_on_hit_procs(
+    registry: Res<ProcRegistry>,
+    mut rng: ResMut<RunRng>,
+    mut dealt_events: EventReader<DamageDealtEvent>,
+    mut proc_actions: EventWriter<ProcAction>,
+    inventories: Query<&Inventory>,
+) {
+    for hit in dealt_events.read() {
+        let Some(attacker) = hit.attacker else {
+            continue;
+        };
+        let Ok(inventory) = inventories.get(attacker) else {
+            continue;
+        };
+        proc_actions.send_batch(registry.roll_on_hit(
+            inventory,
+            attacker,
+            hit,
+            rng.stream(RngStream::Proc),
+        ));
+    }
+}
+
+pub(super) fn roll_on_kill_procs(
+    registry: Res<ProcRegistry>,
+    mut rng: ResMut<RunRng>,
+    mut kill_events: EventReader<KillEvent>,
+    mut proc_actions: EventWriter<ProcAction>,
+    inventories: Query<&Inventory>,
+) {
+    for kill in kill_events.read() {
+        let Some(attacker) = kill.attacker else {
+            continue;
+        };
+        let Ok(inventory) = inventories.get(attacker) else {
+            continue;
+        };
+        proc_actions.send_batch(registry.roll_on_kill(
+            inventory,
+            attacker,
+            kill,
+            rng.stream(RngStream::Proc),
+        ));
+    }
+}
+
+/// A bomb stuck to an enemy, explodes around its target once the timer runs out.
+#[derive(Component)]
+pub struct StickyBomb {
+    attacker: Entity,
+    target: Entity,
+    amount: f32,
+    timer: Timer,
+    proc_chain: ProcChainMask,
+}
+
+const STICKY_BOMB_RADIUS: f32 = 14.0;
+const MISSILE_SPEED: f32 = 160.0;
+const MISSILE_TURN_RATE: f32 = 8.0;
+
+/// Only survivors carry items for now, so chain lightning only ever looks for enemies.
+fn enemies_in_radius(
+    spatial_query: &SpatialQuery,
+    positions: &Query<&Position, With<Health>>,
+    center: Vec2,
+    radius: f32,
+) -> Vec<(Entity, f32)> {
+    let mut enemies: Vec<(Entity, f32)> = spatial_query
+        .shape_intersections(
+            &Collider::ball(radius),
+            center,
+            0.0,
+            SpatialQueryFilter::new().with_masks([Layer::Enemy]),
+        )
+        .into_iter()
+        .filter_map(|entity| {
+            positions
+                .get(entity)
+                .ok()
+                .map(|position| (entity, position.distance_squared(center)))
+        })
+        .collect();
+    // Closest first, so the lightning prefers nearby targets.
+    enemies.sort_by(|a, b| a.1.total_cmp(&b.1));
+    enemies
+}
+
+pub(super) fn execute_proc_actions(
+    mut commands: Commands,
+    spatial_query: SpatialQuery,
+    mut proc_actions: EventReader<ProcAction>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut explosions: EventWriter<ExplosionEvent>,
+    positions: Query<&Position, With<Health>>,
+) {
+    for action in proc_actions.read() {
+        match *action {
+            ProcAction::Damage {
+                attacker,
+                target,
+                amount,
+                proc_coefficient,
+                proc_chain,
+            } => {
+                damage_events.send(
+                    DamageEvent::new(Some(attacker), target, amount)
+                        .with_proc_coefficient(proc_coefficient)
+                        .with_proc_chain(proc_chain),
+                );
+            }
+            ProcAction::Missile {
+                attacker,
+                target,
+                amount,
+                proc_coefficient,
+                proc_chain,
+            } => {
+                let Ok(attacker_position) = positions.get(attacker) else {
+                    continue;
+                };
+                // Missiles launch upwards and curve into their target.
+                commands.spawn((
+                    Name::new("AtgMissile"),
+                    ProjectileBundle::new(
+                        Team::Player,
+                        Some(attacker),
+                        attacker_position.0,
+                        Vec2::Y * MISSILE_SPEED,
+                        amount,
+                    )
+                    .with_size(Vec2::new(2.0, 4.0))
+                    .with_proc(proc_coefficient, proc_chain),
+                    Homing {
+                        target,
+                        turn_rate: MISSILE_TURN_RATE,
+                    },
+                ));
+            }
+            ProcAction::ChainLightning {
+                attacker,
+                origin,
+                amount,
+                bounces,
+                radius,
+                proc_coefficient,
+                proc_chain,
+            } => {
+                let Ok(origin_position) = positions.get(origin) else {
+                    continue;
+                };
+                for (target, _) in
+                    enemies_in_radius(&spatial_query, &positions, origin_position.0, radius)
+                        .into_iter()
+                        .filter(|(target, _)| *target != origin)
+                        .take(bounces as usize)
+                {
+                    damage_events.send(
+                        DamageEvent::new(Some(attacker), target, amount)
+                            .with_proc_coefficient(proc_coefficient)
+                            .with_proc_chain(proc_chain),
+                    );
+                }
+            }
+            ProcAction::StickyBomb {
+                attacker,
+                target,
+                amount,
+                delay,
+                proc_chain,
+            } => {
+                commands.spawn((
+                    Name::new("StickyBomb"),
+                    RunEntity,
+                    StickyBomb {
+                        attacker,
+                        target,
+                        amount,
+                        timer: Timer::from_seconds(delay, TimerMode::Once),
+                        proc_chain,
+                    },
+                ));
+            }
+            ProcAction::Explosion {
+                attacker,
+                position,
+                radius,
+                amount,
+                proc_coefficient,
+                proc_chain,
+            } => {
+                explosions.send(ExplosionEvent {
+                    attacker: Some(attacker),
+                    team: Team::Player,
+                    position,
+                    radius,
+                    damage: amount,
+                    proc_coefficient,
+                    proc_chain,
+                });
+            }
+        }
+    }
+}
+
+pub(super) fn detonate_sticky_bombs(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut explosions: EventWriter<ExplosionEvent>,
+    mut bombs: Query<(Entity, &mut StickyBomb)>,
+    positions: Query<&Position, With<Health>>,
+) {
+    for (entity, mut bomb) in &mut bombs {
+        bomb.timer.tick(time.delta());
+        if !bomb.timer.finished() {
+            continue;
+        }
+
+        // A bomb whose target was despawned fizzles out.
+        if let Ok(position) = positions.get(bomb.target) {
+            explosions.send(ExplosionEvent {
+                attacker: Some(bomb.attacker),
+                team: Team::Player,
+                position: position.0,
+                radius: STICKY_BOMB_RADIUS,
+                damage: bomb.amount,
+                proc_coefficient: 0.0,
+                proc_chain: bomb.proc_chain,
+            });
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(index: u32) -> Entity {
+        Entity::from_raw(index)
+    }
+
+    fn atg(ctx: &ProcContext) -> ProcAction {
+        ProcAction::Damage {
+            attacker: ctx.attacker,
+            target: ctx.target,
+            amount: ctx.damage * 3.0,
+            proc_coefficient: 1.0,
+            proc_chain: ctx.proc_chain,
+        }
+    }
+
+    fn explode(ctx: &KillContext) -> ProcAction {
+        ProcAction::Explosion {
+            attacker: ctx.attacker,
+            position: ctx.position,
+            radius: 10.0,
+            amount: ctx.damage,
+            proc_coefficient: 0.0,
+            proc_chain: ctx.proc_chain,
+        }
+    }
+
+    fn registry(chance: f32) -> ProcRegistry {
+        let mut registry = ProcRegistry::default();
+        registry
+            .register_on_hit(ItemId::AtgMissile, chance, atg)
+            .register_on_kill(ItemId::Gasoline, chance, explode);
+        registry
+    }
+
+    fn inventory(item: ItemId, count: u32) -> Inventory {
+        let mut inventory = Inventory::default();
+        inventory.add(item, count);
+        inventory
+    }
+
+    fn hit() -> DamageDealtEvent {
+        landed(DamageEvent::new(Some(entity(1)), entity(2), 10.0))
+    }
+
+    /// The event sent for `hit` if nothing blocked it.
+    fn landed(hit: DamageEvent) -> DamageDealtEvent {
+        DamageDealtEvent {
+            attacker: hit.attacker,
+            target: hit.target,
+            position: Vec2::ZERO,
+            amount: hit.amount,
+            crit: hit.crit,
+            proc_coefficient: hit.proc_coefficient,
+            proc_chain: hit.proc_chain,
+        }
+    }
+
+    /// Feeds the actions produced by a proc back in as hits, the way the game does.
+    fn resolve_chain(
+        registry: &ProcRegistry,
+        inventory: &Inventory,
+        first_hit: DamageDealtEvent,
+        rng: &mut Rng,
+    ) -> usize {
+        let mut pending = vec![first_hit];
+        let mut procs = 0;
+        while let Some(hit) = pending.pop() {
+            for action in registry.roll_on_hit(inventory, entity(1), &hit, rng) {
+                procs += 1;
+                if let ProcAction::Damage {
+                    target,
+                    amount,
+                    proc_coefficient,
+                    proc_chain,
+                    ..
+                } = action
+                {
+                    pending.push(landed(
+                        DamageEvent::new(Some(entity(1)), target, amount)
+                            .with_proc_coefficient(proc_coefficient)
+                            .with_proc_chain(proc_chain),
+                    ));
+                }
+            }
+            assert!(procs < 100, "proc chain did not terminate");
+        }
+        procs
+    }
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let registry = registry(0.3);
+        let inventory = inventory(ItemId::AtgMissile, 1);
+        let mut a = Rng::new(1234);
+        let mut b = Rng::new(1234);
+
+        for _ in 0..1000 {
+            assert_eq!(
+                registry.roll_on_hit(&inventory, entity(1), &hit(), &mut a),
+                registry.roll_on_hit(&inventory, entity(1), &hit(), &mut b)
+            );
+        }
+    }
+
+    #[test]
+    fn chance_scales_with_stack_and_coefficient() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+        assert!(close(proc_chance(0.1, 1, 1.0), 0.1));
+        assert!(close(proc_chance(0.1, 3, 1.0), 0.3));
+        assert!(close(proc_chance(0.1, 3, 0.5), 0.15));
+        assert_eq!(proc_chance(0.1, 20, 1.0), 1.0);
+        assert_eq!(proc_chance(0.1, 0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn proc_rate_matches_chance() {
+        let registry = registry(0.1);
+        let inventory = inventory(ItemId::AtgMissile, 2);
+        let mut rng = Rng::new(42);
+
+        let procs = (0..10_000)
+            .filter(|_| {
+                !registry
+                    .roll_on_hit(&inventory, entity(1), &hit(), &mut rng)
+                    .is_empty()
+            })
+            .count();
+        assert!((1800..2200).contains(&procs), "{procs} procs out of 10000");
+    }
+
+    #[test]
+    fn zero_coefficient_never_procs() {
+        let registry = registry(1.0);
+        let inventory = inventory(ItemId::AtgMissile, 10);
+        let mut rng = Rng::new(7);
+
+        for _ in 0..1000 {
+            let hit = landed(
+                DamageEvent::new(Some(entity(1)), entity(2), 10.0).with_proc_coefficient(0.0),
+            );
+            assert!(registry
+                .roll_on_hit(&inventory, entity(1), &hit, &mut rng)
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn missing_item_never_procs() {
+        let registry = registry(1.0);
+        let inventory = inventory(ItemId::Ukulele, 1);
+        let mut rng = Rng::new(7);
+
+        assert!(registry
+            .roll_on_hit(&inventory, entity(1), &hit(), &mut rng)
+            .is_empty());
+    }
+
+    #[test]
+    fn proc_adds_item_to_chain() {
+        let registry = registry(1.0);
+        let inventory = inventory(ItemId::AtgMissile, 1);
+        let mut rng = Rng::new(7);
+
+        let actions = registry.roll_on_hit(&inventory, entity(1), &hit(), &mut rng);
+        assert_eq!(
+            actions,
+            vec![ProcAction::Damage {
+                attacker: entity(1),
+                target: entity(2),
+                amount: 30.0,
+                proc_coefficient: 1.0,
+                proc_chain: ProcChainMask::default().with(ItemId::AtgMissile),
+            }]
+        );
+    }
+
+    #[test]
+    fn proc_cannot_retrigger_itself() {
+        let registry = registry(1.0);
+        let inventory = inventory(ItemId::AtgMissile, 5);
+        let mut rng = Rng::new(99);
+
+        // A guaranteed proc with a full coefficient would loop forever without the chain mask.
+        assert_eq!(resolve_chain(&registry, &inventory, hit(), &mut rng), 1);
+    }
+
+    #[test]
+    fn different_items_can_chain_once() {
+        fn other(ctx: &ProcContext) -> ProcAction {
+            atg(ctx)
+        }
+
+        let mut registry = registry(1.0);
+        registry.register_on_hit(ItemId::Ukulele, 1.0, other);
+        let mut inventory = inventory(ItemId::AtgMissile, 1);
+        inventory.add(ItemId::Ukulele, 1);
+        let mut rng = Rng::new(5);
+
+        // Hit -> AtG + Ukulele, AtG -> Ukulele, Ukulele -> AtG, then both masks are full.
+        assert_eq!(resolve_chain(&registry, &inventory, hit(), &mut rng), 4);
+    }
+
+    #[test]
+    fn on_kill_respects_chain() {
+        let registry = registry(1.0);
+        let inventory = inventory(ItemId::Gasoline, 1);
+        let mut rng = Rng::new(3);
+        let kill = KillEvent {
+            attacker: Some(entity(1)),
+            victim: entity(2),
+            position: Vec2::new(4.0, 2.0),
+            damage: 12.0,
+            proc_coefficient: 1.0,
+            proc_chain: ProcChainMask::default(),
+        };
+
+        let actions = registry.roll_on_kill(&inventory, entity(1), &kill, &mut rng);
+        assert_eq!(actions.len(), 1);
+
+        let chained = KillEvent {
+            proc_chain: ProcChainMask::default().with(ItemId::Gasoline),
+            ..kill
+        };
+        assert!(registry
+            .roll_on_kill(&inventory, entity(1), &chained, &mut rng)
+            .is_empty());
+    }
+}
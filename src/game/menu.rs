@@ -0,0 +1,274 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+use super::input::{Action, ActionState};
+use super::player::{PlayerCount, MAX_PLAYERS};
+use super::run_rng::{random_seed, RunRng};
+use super::save::{load_saved_run, SavedRun};
+use super::util::{despawn_with, RunEntity};
+use crate::{AppState, GameFont};
+
+const MAX_SEED_DIGITS: usize = 19;
+
+#[derive(Component)]
+struct OnMenuScreen;
+
+#[derive(Component)]
+struct OnGameOverScreen;
+
+#[derive(Component)]
+struct SeedText;
+
+#[derive(Component)]
+struct PlayerCountText;
+
+/// The seed typed into the main menu, empty for a random seed.
+#[derive(Resource, Default)]
+pub struct SeedInput(pub String);
+
+/// Menu navigation from the keyboard or any gamepad.
+#[derive(SystemParam)]
+pub(crate) struct MenuInput<'w> {
+    keys: Res<'w, Input<KeyCode>>,
+    gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    actions: Res<'w, ActionState>,
+}
+
+impl MenuInput<'_> {
+    fn gamepad_just_pressed(&self, button_type: GamepadButtonType) -> bool {
+        self.gamepad_buttons
+            .get_just_pressed()
+            .any(|button| button.button_type == button_type)
+    }
+
+    fn axis_just_pressed(&self, action: Action, positive: bool) -> bool {
+        self.actions.just_pressed(action) && (self.actions.value(action) > 0.0) == positive
+    }
+
+    pub fn confirm(&self) -> bool {
+        self.keys.just_pressed(KeyCode::Return)
+            || self.gamepad_just_pressed(GamepadButtonType::South)
+    }
+
+    pub fn resume(&self) -> bool {
+        self.keys.just_pressed(KeyCode::C) || self.gamepad_just_pressed(GamepadButtonType::North)
+    }
+
+    pub fn back(&self) -> bool {
+        self.keys.just_pressed(KeyCode::Escape)
+            || self.gamepad_just_pressed(GamepadButtonType::East)
+    }
+
+    pub fn up(&self) -> bool {
+        self.axis_just_pressed(Action::Climb, true)
+    }
+
+    pub fn down(&self) -> bool {
+        self.axis_just_pressed(Action::Climb, false)
+    }
+
+    pub fn left(&self) -> bool {
+        self.axis_just_pressed(Action::Move, false)
+    }
+
+    pub fn right(&self) -> bool {
+        self.axis_just_pressed(Action::Move, true)
+    }
+}
+
+fn seed_label(input: &SeedInput) -> String {
+    if input.0.is_empty() {
+        "Seed: random".to_string()
+    } else {
+        format!("Seed: {}", input.0)
+    }
+}
+
+pub(crate) fn screen_root(background: Color) -> NodeBundle {
+    NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(12.0),
+            ..default()
+        },
+        background_color: background.into(),
+        ..default()
+    }
+}
+
+pub(crate) fn text_style(game_font: &GameFont, font_size: f32, color: Color) -> TextStyle {
+    TextStyle {
+        font: game_font.0.clone(),
+        font_size,
+        color,
+    }
+}
+
+fn player_count_label(player_count: PlayerCount) -> String {
+    format!("< Players: {} >", player_count.0)
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    game_font: Res<GameFont>,
+    seed_input: Res<SeedInput>,
+    player_count: Res<PlayerCount>,
+    saved_run: Option<Res<SavedRun>>,
+) {
+    commands
+        .spawn((
+            Name::new("MainMenu"),
+            OnMenuScreen,
+            screen_root(crate::CLEAR_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Risk of Rust",
+                text_style(&game_font, 48.0, Color::WHITE),
+            ));
+            parent.spawn((
+                SeedText,
+                TextBundle::from_section(
+                    seed_label(&seed_input),
+                    text_style(&game_font, 24.0, Color::YELLOW),
+                ),
+            ));
+            parent.spawn((
+                PlayerCountText,
+                TextBundle::from_section(
+                    player_count_label(*player_count),
+                    text_style(&game_font, 24.0, Color::WHITE),
+                ),
+            ));
+            if saved_run.is_some() {
+                parent.spawn(TextBundle::from_section(
+                    "C (Y) to continue the saved run, starting a new one discards it",
+                    text_style(&game_font, 16.0, Color::GREEN),
+                ));
+            }
+            parent.spawn(TextBundle::from_section(
+                "Type a seed, Backspace to clear, Enter (A) to start, Tab (Select) for settings",
+                text_style(&game_font, 16.0, Color::GRAY),
+            ));
+        });
+}
+
+fn seed_input(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut seed_input: ResMut<SeedInput>,
+    mut seed_text: Query<&mut Text, With<SeedText>>,
+) {
+    for ev in characters.read() {
+        if ev.char.is_ascii_digit() && seed_input.0.len() < MAX_SEED_DIGITS {
+            seed_input.0.push(ev.char);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        seed_input.0.pop();
+    }
+
+    if seed_input.is_changed() {
+        for mut text in &mut seed_text {
+            text.sections[0].value = seed_label(&seed_input);
+        }
+    }
+}
+
+/// Picks the number of local players, each extra player needs a gamepad.
+fn select_player_count(
+    menu_input: MenuInput,
+    mut player_count: ResMut<PlayerCount>,
+    mut texts: Query<&mut Text, With<PlayerCountText>>,
+) {
+    if menu_input.left() && player_count.0 > 1 {
+        player_count.0 -= 1;
+    }
+    if menu_input.right() && player_count.0 < MAX_PLAYERS {
+        player_count.0 += 1;
+    }
+
+    if player_count.is_changed() {
+        for mut text in &mut texts {
+            text.sections[0].value = player_count_label(*player_count);
+        }
+    }
+}
+
+fn start_run(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    menu_input: MenuInput,
+    seed_input: Res<SeedInput>,
+    saved_run: Option<Res<SavedRun>>,
+    mut player_count: ResMut<PlayerCount>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if menu_input.confirm() {
+        let seed = seed_input.0.parse().unwrap_or_else(|_| random_seed());
+        commands.insert_resource(RunRng::new(seed));
+        commands.remove_resource::<SavedRun>();
+        next_state.set(AppState::InGame);
+    } else if let Some(saved_run) = saved_run.filter(|_| menu_input.resume()) {
+        // The RNG and everything else comes from the save once the run starts.
+        player_count.0 = saved_run.player_count();
+        next_state.set(AppState::InGame);
+    } else if keyboard_input.just_pressed(KeyCode::Tab)
+        || menu_input.gamepad_just_pressed(GamepadButtonType::Select)
+    {
+        next_state.set(AppState::Settings);
+    }
+}
+
+fn spawn_game_over(mut commands: Commands, game_font: Res<GameFont>, run_rng: Res<RunRng>) {
+    commands
+        .spawn((
+            Name::new("GameOver"),
+            OnGameOverScreen,
+            screen_root(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Game Over",
+                text_style(&game_font, 48.0, Color::RED),
+            ));
+            parent.spawn(TextBundle::from_section(
+                format!("Seed: {}", run_rng.seed()),
+                text_style(&game_font, 24.0, Color::YELLOW),
+            ));
+            parent.spawn(TextBundle::from_section(
+                "Press Enter to return to the menu",
+                text_style(&game_font, 16.0, Color::GRAY),
+            ));
+        });
+}
+
+fn return_to_menu(menu_input: MenuInput, mut next_state: ResMut<NextState<AppState>>) {
+    if menu_input.confirm() {
+        next_state.set(AppState::Menu);
+    }
+}
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SeedInput>()
+            .add_systems(OnEnter(AppState::Menu), spawn_menu.after(load_saved_run))
+            .add_systems(
+                Update,
+                (seed_input, select_player_count, start_run).run_if(in_state(AppState::Menu)),
+            )
+            .add_systems(OnExit(AppState::Menu), despawn_with::<OnMenuScreen>)
+            .add_systems(OnEnter(AppState::GameOver), spawn_game_over)
+            .add_systems(Update, return_to_menu.run_if(in_state(AppState::GameOver)))
+            .add_systems(
+                OnExit(AppState::GameOver),
+                (despawn_with::<OnGameOverScreen>, despawn_with::<RunEntity>),
+            );
+    }
+}
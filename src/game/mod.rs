@@ -1,8 +1,30 @@
+pub mod audio;
+pub mod camera;
 pub mod clock;
+pub mod combat;
+#[cfg(feature = "dev")]
+pub mod console;
 pub mod enemy;
+pub mod floating_text;
+pub mod gravity_zone;
+pub mod hitbox;
+pub mod hud;
+pub mod input;
+pub mod items;
+pub mod menu;
+pub mod net;
+pub mod pause_menu;
 pub mod physics_layers;
 pub mod player;
 pub mod player_controller;
+pub mod profile;
+pub mod projectile;
+pub mod replay;
 pub mod rope;
+pub mod run_rng;
+pub mod save;
+pub mod settings;
+pub mod settings_menu;
+pub mod skills;
 pub mod stats;
 pub mod util;
@@ -0,0 +1,360 @@
+use std::collections::VecDeque;
+use std::net::{SocketAddr, UdpSocket};
+
+use bevy::{app::AppExit, ecs::query::Has, prelude::*};
+use bevy_xpbd_2d::prelude::*;
+
+use super::protocol::{
+    apply_world_delta, ClientMessage, InputIntent, ServerMessage, WorldState, PROTOCOL_VERSION,
+};
+use super::{bind, receive_all, send, NetId, SNAPSHOT_HISTORY, TIMEOUT_SECS};
+use crate::game::combat::{Dead, Health};
+use crate::game::hud::HudTarget;
+use crate::game::input::{ActionState, ActionStateSet};
+use crate::game::items::Inventory;
+use crate::game::player::{PlayerCount, PlayerIndex};
+use crate::game::player_controller::{CharacterController, ControllerGravity, EffectiveGravity};
+use crate::game::run_rng::RunRng;
+use crate::AppState;
+
+/// How far behind the newest snapshot entities are shown, so there is usually a newer snapshot
+/// to interpolate towards.
+const INTERPOLATION_DELAY: f32 = 0.1;
+const HELLO_INTERVAL: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Connection {
+    Connecting,
+    Connected {
+        player_index: u32,
+    },
+    /// The host was full or went away, the client stays put until it's restarted.
+    Closed,
+}
+
+struct Snapshot {
+    tick: u32,
+    time: f32,
+    world: WorldState,
+}
+
+/// The receiving end of an online game, showing the host's simulation.
+#[derive(Resource)]
+pub struct NetClient {
+    socket: UdpSocket,
+    server: SocketAddr,
+    connection: Connection,
+    /// Received snapshots, oldest first.
+    snapshots: VecDeque<Snapshot>,
+    /// Host time minus local time, smoothed over the received snapshots.
+    clock_offset: Option<f32>,
+    /// Network ids the host despawned, whose replicas are yet to be despawned.
+    removed: Vec<u32>,
+    last_heard: f64,
+    hello_timer: Timer,
+}
+
+impl NetClient {
+    /// The player this client controls, once the host has welcomed it.
+    pub fn player_index(&self) -> Option<u32> {
+        match self.connection {
+            Connection::Connected { player_index } => Some(player_index),
+            _ => None,
+        }
+    }
+
+    fn latest_tick(&self) -> Option<u32> {
+        self.snapshots.back().map(|snapshot| snapshot.tick)
+    }
+
+    fn receive_snapshot(
+        &mut self,
+        tick: u32,
+        time: f32,
+        baseline: Option<u32>,
+        deltas: &[(u32, super::protocol::EntityDelta)],
+        now: f32,
+    ) {
+        if self.latest_tick().is_some_and(|latest| tick <= latest) {
+            return;
+        }
+        let baseline = match baseline {
+            Some(baseline) => {
+                let Some(snapshot) = self.snapshots.iter().find(|s| s.tick == baseline) else {
+                    // Already dropped, the host switches to a newer baseline once it sees our ack.
+                    return;
+                };
+                Some(&snapshot.world)
+            }
+            None => None,
+        };
+
+        let world = apply_world_delta(baseline, deltas);
+        self.removed.extend(
+            deltas
+                .iter()
+                .filter(|(_, delta)| delta.removed)
+                .map(|(id, _)| *id),
+        );
+        self.snapshots.push_back(Snapshot { tick, time, world });
+        if self.snapshots.len() > SNAPSHOT_HISTORY {
+            self.snapshots.pop_front();
+        }
+
+        let offset = time - now;
+        self.clock_offset = Some(match self.clock_offset {
+            Some(current) => current + (offset - current) * 0.1,
+            None => offset,
+        });
+    }
+}
+
+fn client_receive(
+    mut client: ResMut<NetClient>,
+    time: Res<Time<Real>>,
+    mut commands: Commands,
+    mut player_count: ResMut<PlayerCount>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let now = time.elapsed_seconds_f64();
+
+    for (bytes, from) in receive_all(&client.socket) {
+        if from != client.server {
+            continue;
+        }
+        let Some(message) = ServerMessage::decode(&bytes) else {
+            continue;
+        };
+        client.last_heard = now;
+
+        match message {
+            ServerMessage::Welcome {
+                player_index,
+                player_count: count,
+                seed,
+            } => {
+                if client.connection != Connection::Connecting {
+                    continue;
+                }
+                info!("Joined {} as player {}", client.server, player_index + 1);
+                client.connection = Connection::Connected { player_index };
+                player_count.0 = count;
+                commands.insert_resource(RunRng::new(seed));
+                if *state.get() != AppState::InGame {
+                    next_state.set(AppState::InGame);
+                }
+            }
+            ServerMessage::Full => {
+                if client.connection == Connection::Connecting {
+                    error!("{} has no free player slot", client.server);
+                    client.connection = Connection::Closed;
+                }
+            }
+            ServerMessage::Snapshot {
+                tick,
+                time: host_time,
+                baseline,
+                deltas,
+            } => {
+                if client.player_index().is_some() {
+                    let now = time.elapsed_seconds();
+                    client.receive_snapshot(tick, host_time, baseline, &deltas, now);
+                }
+            }
+        }
+    }
+
+    if client.player_index().is_some() && now - client.last_heard > TIMEOUT_SECS {
+        warn!("Lost connection to {}", client.server);
+        client.connection = Connection::Closed;
+        next_state.set(AppState::GameOver);
+    }
+}
+
+/// Says hello until the host answers, then sends the local input every frame.
+fn client_send(
+    mut client: ResMut<NetClient>,
+    time: Res<Time<Real>>,
+    action_state: Res<ActionState>,
+) {
+    let message = match client.connection {
+        Connection::Connecting => {
+            if !client.hello_timer.tick(time.delta()).just_finished() {
+                return;
+            }
+            ClientMessage::Hello {
+                version: PROTOCOL_VERSION,
+            }
+        }
+        Connection::Connected { .. } => ClientMessage::Input {
+            ack: client.latest_tick(),
+            intent: InputIntent::from_action_state(&action_state),
+        },
+        Connection::Closed => return,
+    };
+    send(&client.socket, &message.encode(), client.server);
+}
+
+/// Turns replicated entities into puppets of the host, and puts the HUD on the local player.
+fn make_proxies(
+    mut commands: Commands,
+    client: Res<NetClient>,
+    added: Query<(Entity, Option<&PlayerIndex>), Added<NetId>>,
+) {
+    for (entity, player_index) in &added {
+        let mut entity = commands.entity(entity);
+        entity
+            .remove::<(CharacterController, ControllerGravity, EffectiveGravity)>()
+            .insert(RigidBody::Static);
+        if let Some(player_index) = player_index {
+            if Some(player_index.0) == client.player_index() {
+                entity.insert(HudTarget);
+            } else {
+                entity.remove::<HudTarget>();
+            }
+        }
+    }
+}
+
+/// Despawns the replicas of entities the host despawned.
+fn despawn_removed(
+    mut commands: Commands,
+    mut client: ResMut<NetClient>,
+    replicated: Query<(Entity, &NetId)>,
+) {
+    if client.removed.is_empty() {
+        return;
+    }
+    for (entity, id) in &replicated {
+        if client.removed.contains(&id.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    client.removed.clear();
+}
+
+/// Moves replicated entities to where the host had them slightly in the past.
+///
+/// Clients don't deal damage themselves, so whether an entity is [`Dead`] follows its health.
+#[allow(clippy::type_complexity)]
+fn apply_snapshots(
+    mut commands: Commands,
+    client: Res<NetClient>,
+    time: Res<Time<Real>>,
+    mut replicated: Query<(
+        Entity,
+        Has<Dead>,
+        &NetId,
+        &mut Position,
+        Option<&mut LinearVelocity>,
+        Option<&mut Health>,
+        Option<&mut Inventory>,
+    )>,
+) {
+    let (Some(latest), Some(clock_offset)) = (client.snapshots.back(), client.clock_offset) else {
+        return;
+    };
+    let render_time = time.elapsed_seconds() + clock_offset - INTERPOLATION_DELAY;
+
+    // The snapshots either side of the render time, or the newest one if we ran past it.
+    let next = client
+        .snapshots
+        .iter()
+        .position(|snapshot| snapshot.time >= render_time);
+    let (from, to, t) = match next {
+        Some(next) if next > 0 => {
+            let from = &client.snapshots[next - 1];
+            let to = &client.snapshots[next];
+            let t = (render_time - from.time) / (to.time - from.time).max(f32::EPSILON);
+            (from, to, t.clamp(0.0, 1.0))
+        }
+        Some(next) => (&client.snapshots[next], &client.snapshots[next], 1.0),
+        None => (latest, latest, 1.0),
+    };
+
+    for (entity, is_dead, id, mut position, velocity, health, inventory) in &mut replicated {
+        let Some(state) = to.world.get(&id.0) else {
+            continue;
+        };
+        position.0 = match from.world.get(&id.0) {
+            Some(previous) => previous.position.lerp(state.position, t),
+            None => state.position,
+        };
+        if let Some(mut velocity) = velocity {
+            velocity.0 = state.velocity;
+        }
+
+        // Gameplay state isn't interpolated, and only written when it changed so change
+        // detection (e.g. the HUD item strip) keeps working.
+        let Some(latest) = latest.world.get(&id.0) else {
+            continue;
+        };
+        if let (Some(mut health), Some((current, max))) = (health, latest.health) {
+            if health.current != current || health.max != max {
+                health.current = current;
+                health.max = max;
+            }
+            if health.is_dead() && !is_dead {
+                commands.entity(entity).insert(Dead);
+            } else if !health.is_dead() && is_dead {
+                commands.entity(entity).remove::<Dead>();
+            }
+        }
+        if let (Some(mut inventory), Some(stacks)) = (inventory, &latest.inventory) {
+            if inventory.stacks() != stacks.as_slice() {
+                *inventory = Inventory::from(stacks.clone());
+            }
+        }
+    }
+}
+
+fn say_goodbye(client: Res<NetClient>, mut exit_events: EventReader<AppExit>) {
+    if exit_events.read().next().is_some() && client.player_index().is_some() {
+        send(
+            &client.socket,
+            &ClientMessage::Goodbye.encode(),
+            client.server,
+        );
+    }
+}
+
+pub(super) fn build(app: &mut App, server: SocketAddr) {
+    let local = if server.is_ipv4() {
+        SocketAddr::from(([0, 0, 0, 0], 0))
+    } else {
+        SocketAddr::from(([0u16; 8], 0))
+    };
+    let socket = match bind(local) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("Failed to open a socket, staying offline: {err}");
+            return;
+        }
+    };
+    info!("Connecting to {server}");
+
+    let mut hello_timer = Timer::from_seconds(HELLO_INTERVAL, TimerMode::Repeating);
+    // Say hello on the very first frame.
+    hello_timer.set_elapsed(hello_timer.duration());
+
+    app.insert_resource(NetClient {
+        socket,
+        server,
+        connection: Connection::Connecting,
+        snapshots: VecDeque::new(),
+        clock_offset: None,
+        removed: Vec::new(),
+        last_heard: 0.0,
+        hello_timer,
+    })
+    .add_systems(
+        PreUpdate,
+        (client_receive, client_send).chain().after(ActionStateSet),
+    )
+    .add_systems(
+        Update,
+        (make_proxies, despawn_removed, apply_snapshots).run_if(in_state(AppState::InGame)),
+    )
+    .add_systems(Last, say_goodbye);
+}
@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::net::{SocketAddr, UdpSocket};
+
+use bevy::{ecs::query::Has, prelude::*};
+
+use super::protocol::{world_delta, ClientMessage, InputIntent, ServerMessage, WorldState};
+use super::{
+    bind, capture_world, receive_all, send, ReplicatedQuery, SNAPSHOT_HISTORY, SNAPSHOT_RATE,
+    TIMEOUT_SECS,
+};
+use crate::game::input::{update_action_state, ActionState, ActionStateSet, RemoteInput};
+use crate::game::player::{PlayerCount, PlayerIndex};
+use crate::game::run_rng::RunRng;
+use crate::AppState;
+
+struct ClientConnection {
+    addr: SocketAddr,
+    player_index: u32,
+    /// The newest snapshot the client acknowledged.
+    ack: Option<u32>,
+    last_heard: f64,
+    intent: InputIntent,
+}
+
+/// The authoritative end of an online game.
+///
+/// Clients take over the players the host isn't using, from index 1 up to the [`PlayerCount`]
+/// picked in the main menu.
+#[derive(Resource)]
+pub struct NetHost {
+    socket: UdpSocket,
+    clients: Vec<ClientConnection>,
+    tick: u32,
+    /// Recently sent snapshots, the baselines clients can acknowledge.
+    history: VecDeque<(u32, WorldState)>,
+    snapshot_timer: Timer,
+}
+
+impl NetHost {
+    /// The lowest player index no client has claimed yet.
+    fn free_player_index(&self, player_count: u32) -> Option<u32> {
+        (1..player_count).find(|index| self.clients.iter().all(|c| c.player_index != *index))
+    }
+}
+
+fn host_receive(
+    mut host: ResMut<NetHost>,
+    time: Res<Time<Real>>,
+    player_count: Res<PlayerCount>,
+    run_rng: Res<RunRng>,
+) {
+    let now = time.elapsed_seconds_f64();
+    let host = &mut *host;
+
+    for (bytes, from) in receive_all(&host.socket) {
+        let Some(message) = ClientMessage::decode(&bytes) else {
+            continue;
+        };
+        let client = host.clients.iter().position(|c| c.addr == from);
+
+        match (message, client) {
+            (ClientMessage::Hello { version }, client) => {
+                if version != super::protocol::PROTOCOL_VERSION {
+                    warn!("Ignoring {from}, it speaks protocol version {version}");
+                    continue;
+                }
+                // A client resends its hello until the welcome arrives.
+                let player_index = match client {
+                    Some(client) => Some(host.clients[client].player_index),
+                    None => host.free_player_index(player_count.0),
+                };
+                let Some(player_index) = player_index else {
+                    send(&host.socket, &ServerMessage::Full.encode(), from);
+                    continue;
+                };
+                if client.is_none() {
+                    info!("{from} joined as player {}", player_index + 1);
+                    host.clients.push(ClientConnection {
+                        addr: from,
+                        player_index,
+                        ack: None,
+                        last_heard: now,
+                        intent: InputIntent::default(),
+                    });
+                }
+                let welcome = ServerMessage::Welcome {
+                    player_index,
+                    player_count: player_count.0,
+                    seed: run_rng.seed(),
+                };
+                send(&host.socket, &welcome.encode(), from);
+            }
+            (ClientMessage::Input { ack, intent }, Some(client)) => {
+                let client = &mut host.clients[client];
+                client.last_heard = now;
+                client.intent = intent;
+                // Packets can arrive out of order, never go back to an older baseline.
+                if ack > client.ack {
+                    client.ack = ack;
+                }
+            }
+            (ClientMessage::Goodbye, Some(client)) => {
+                let client = host.clients.remove(client);
+                info!("{} left", client.addr);
+            }
+            _ => {}
+        }
+    }
+
+    host.clients.retain(|client| {
+        let alive = now - client.last_heard < TIMEOUT_SECS;
+        if !alive {
+            info!("{} timed out", client.addr);
+        }
+        alive
+    });
+}
+
+/// Drives remote players from their client's latest intent.
+fn apply_remote_input(
+    mut commands: Commands,
+    host: Res<NetHost>,
+    mut players: Query<(Entity, &PlayerIndex, &mut ActionState, Has<RemoteInput>)>,
+) {
+    for (entity, index, mut action_state, is_remote) in &mut players {
+        match host.clients.iter().find(|c| c.player_index == index.0) {
+            Some(client) => {
+                client.intent.apply(&mut action_state);
+                if !is_remote {
+                    commands.entity(entity).insert(RemoteInput);
+                }
+            }
+            None if is_remote => {
+                *action_state = ActionState::default();
+                commands.entity(entity).remove::<RemoteInput>();
+            }
+            None => {}
+        }
+    }
+}
+
+/// Sends every client a snapshot, stamped with real time so clients keep interpolating at the
+/// right pace while the host is paused or slowed down.
+fn host_send_snapshots(
+    mut host: ResMut<NetHost>,
+    time: Res<Time<Real>>,
+    replicated: ReplicatedQuery,
+) {
+    if !host.snapshot_timer.tick(time.delta()).just_finished() || host.clients.is_empty() {
+        return;
+    }
+
+    let host = &mut *host;
+    host.tick += 1;
+    let world = capture_world(&replicated);
+
+    for client in &host.clients {
+        // Fall back to a full snapshot if the acknowledged one is too old to still be around.
+        let baseline = client.ack.and_then(|ack| {
+            host.history
+                .iter()
+                .find(|(tick, _)| *tick == ack)
+                .map(|(tick, world)| (*tick, world))
+        });
+        let message = ServerMessage::Snapshot {
+            tick: host.tick,
+            time: time.elapsed_seconds(),
+            baseline: baseline.map(|(tick, _)| tick),
+            deltas: world_delta(baseline.map(|(_, world)| world), &world),
+        };
+        send(&host.socket, &message.encode(), client.addr);
+    }
+
+    host.history.push_back((host.tick, world));
+    if host.history.len() > SNAPSHOT_HISTORY {
+        host.history.pop_front();
+    }
+}
+
+pub(super) fn build(app: &mut App, port: u16) {
+    let socket = match bind(SocketAddr::from(([0, 0, 0, 0], port))) {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("Failed to host on port {port}, staying offline: {err}");
+            return;
+        }
+    };
+    info!("Hosting on port {port}");
+
+    app.insert_resource(NetHost {
+        socket,
+        clients: Vec::new(),
+        tick: 0,
+        history: VecDeque::new(),
+        snapshot_timer: Timer::from_seconds(1.0 / SNAPSHOT_RATE, TimerMode::Repeating),
+    })
+    .add_systems(
+        PreUpdate,
+        // Part of the set, so everything reading input for the frame sees the remote players'.
+        (host_receive, apply_remote_input)
+            .chain()
+            .in_set(ActionStateSet)
+            .after(update_action_state),
+    )
+    .add_systems(
+        PostUpdate,
+        host_send_snapshots.run_if(in_state(AppState::InGame)),
+    );
+}
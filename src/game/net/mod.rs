@@ -0,0 +1,230 @@
+//! Online co-op over plain UDP.
+//!
+//! The host runs the authoritative simulation. Clients only send their inputs and display the
+//! replicated state, interpolating between snapshots. Start a host and a client on one machine
+//! with:
+//!
+//! ```text
+//! cargo run -- --host 7777
+//! cargo run -- --connect 127.0.0.1:7777
+//! ```
+
+mod client;
+mod host;
+pub mod protocol;
+
+use std::net::{SocketAddr, UdpSocket};
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use self::protocol::{EntityState, WorldState};
+use super::combat::Health;
+use super::items::Inventory;
+
+/// Clients that haven't been heard from for this long are dropped.
+const TIMEOUT_SECS: f64 = 5.0;
+/// Snapshots sent per second.
+const SNAPSHOT_RATE: f32 = 30.0;
+/// The number of past snapshots kept around as delta baselines.
+const SNAPSHOT_HISTORY: usize = 64;
+
+/// Identifies a replicated entity across the network.
+///
+/// Ids must be assigned the same way on the host and its clients, so only entities spawned
+/// deterministically (players, level entities) have one.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub struct NetId(pub u32);
+
+impl NetId {
+    /// Players use their index, level entities start after the last possible player.
+    pub const FIRST_LEVEL_ID: u32 = 16;
+}
+
+/// Systems that decide how the game plays out: damage, deaths, procs and attacks.
+///
+/// Only the host or an offline game runs them. A client would work them out against replicas
+/// shown in the past, it is sent their outcome instead.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct AuthoritySet;
+
+/// How this instance takes part in an online game, parsed from the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetMode {
+    Offline,
+    Host { port: u16 },
+    Client { server: SocketAddr },
+}
+
+impl NetMode {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--host" => {
+                    if let Some(port) = args.next().and_then(|port| port.parse().ok()) {
+                        return NetMode::Host { port };
+                    }
+                }
+                "--connect" => {
+                    if let Some(server) = args.next().and_then(|addr| addr.parse().ok()) {
+                        return NetMode::Client { server };
+                    }
+                }
+                _ => {}
+            }
+        }
+        NetMode::Offline
+    }
+}
+
+fn bind(addr: SocketAddr) -> std::io::Result<UdpSocket> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket)
+}
+
+/// Reads every packet that arrived since the last frame.
+fn receive_all(socket: &UdpSocket) -> Vec<(Vec<u8>, SocketAddr)> {
+    let mut packets = Vec::new();
+    let mut buffer = [0; protocol::MAX_PACKET_SIZE];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((len, from)) => packets.push((buffer[..len].to_vec(), from)),
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+            // Windows reports a previous send to a closed port on the next receive, skip it.
+            Err(err) if err.kind() == std::io::ErrorKind::ConnectionReset => continue,
+            Err(err) => {
+                warn!("Failed to receive packet: {err}");
+                break;
+            }
+        }
+    }
+    packets
+}
+
+fn send(socket: &UdpSocket, bytes: &[u8], to: SocketAddr) {
+    if bytes.len() > protocol::MAX_PACKET_SIZE {
+        warn!("Dropping oversized packet of {} bytes", bytes.len());
+        return;
+    }
+    if let Err(err) = socket.send_to(bytes, to) {
+        warn!("Failed to send packet to {to}: {err}");
+    }
+}
+
+type ReplicatedQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static NetId,
+        &'static Position,
+        Option<&'static LinearVelocity>,
+        Option<&'static Health>,
+        Option<&'static Inventory>,
+    ),
+>;
+
+/// Captures the replicated state of every entity with a [`NetId`].
+fn capture_world(query: &ReplicatedQuery) -> WorldState {
+    query
+        .iter()
+        .map(|(id, position, velocity, health, inventory)| {
+            let state = EntityState {
+                position: position.0,
+                velocity: velocity.map_or(Vec2::ZERO, |v| v.0),
+                health: health.map(|h| (h.current, h.max)),
+                inventory: inventory.map(|i| i.stacks().to_vec()),
+            };
+            (id.0, state)
+        })
+        .collect()
+}
+
+pub struct NetPlugin {
+    pub mode: NetMode,
+}
+
+impl Plugin for NetPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NetId>();
+        app.configure_sets(
+            Update,
+            AuthoritySet.run_if(not(resource_exists::<client::NetClient>())),
+        )
+        .configure_sets(
+            FixedUpdate,
+            AuthoritySet.run_if(not(resource_exists::<client::NetClient>())),
+        );
+        match self.mode {
+            NetMode::Offline => {}
+            NetMode::Host { port } => host::build(app, port),
+            NetMode::Client { server } => client::build(app, server),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::protocol::*;
+    use super::*;
+
+    #[test]
+    fn parses_command_line() {
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(NetMode::from_args(args(&["game"])), NetMode::Offline);
+        assert_eq!(
+            NetMode::from_args(args(&["game", "--host", "7777"])),
+            NetMode::Host { port: 7777 }
+        );
+        assert_eq!(
+            NetMode::from_args(args(&["game", "--connect", "127.0.0.1:7777"])),
+            NetMode::Client {
+                server: "127.0.0.1:7777".parse().unwrap()
+            }
+        );
+    }
+
+    #[test]
+    fn messages_cross_loopback() {
+        let host = bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let client = bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let host_addr = host.local_addr().unwrap();
+        let client_addr = client.local_addr().unwrap();
+
+        let hello = ClientMessage::Hello {
+            version: PROTOCOL_VERSION,
+        };
+        send(&client, &hello.encode(), host_addr);
+
+        let welcome = ServerMessage::Welcome {
+            player_index: 1,
+            player_count: 2,
+            seed: 1234,
+        };
+        // Loopback delivery is quick but not instant, poll for a little while.
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(receive_all(&host));
+            if !received.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let (bytes, from) = received.pop().expect("host received nothing");
+        assert_eq!(from, client_addr);
+        assert_eq!(ClientMessage::decode(&bytes), Some(hello));
+
+        send(&host, &welcome.encode(), from);
+        let mut received = Vec::new();
+        for _ in 0..100 {
+            received.extend(receive_all(&client));
+            if !received.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let (bytes, _) = received.pop().expect("client received nothing");
+        assert_eq!(ServerMessage::decode(&bytes), Some(welcome));
+    }
+}
@@ -0,0 +1,544 @@
+//! The wire format shared by the host and its clients.
+//!
+//! Everything is little endian and hand packed, snapshots only carry the fields that changed
+//! since the last snapshot the client acknowledged.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::game::input::{Action, ActionState};
+use crate::game::items::{ItemId, ItemStack};
+
+pub const PROTOCOL_VERSION: u8 = 2;
+/// Stays below the usual MTU so packets are never fragmented.
+pub const MAX_PACKET_SIZE: usize = 1200;
+
+const HELLO: u8 = 0;
+const INPUT: u8 = 1;
+const GOODBYE: u8 = 2;
+const WELCOME: u8 = 3;
+const FULL: u8 = 4;
+const SNAPSHOT: u8 = 5;
+
+const HAS_POSITION: u8 = 1 << 0;
+const HAS_VELOCITY: u8 = 1 << 1;
+const HAS_HEALTH: u8 = 1 << 2;
+const HAS_INVENTORY: u8 = 1 << 3;
+const REMOVED: u8 = 1 << 4;
+
+pub(crate) struct ByteWriter(pub(crate) Vec<u8>);
+
+impl ByteWriter {
+    fn new(kind: u8) -> Self {
+        Self(vec![kind])
+    }
+
+    pub(crate) fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    pub(crate) fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub(crate) fn f32(&mut self, value: f32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vec2(&mut self, value: Vec2) {
+        self.f32(value.x);
+        self.f32(value.y);
+    }
+}
+
+pub(crate) struct ByteReader<'a>(pub(crate) &'a [u8]);
+
+impl ByteReader<'_> {
+    pub(crate) fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        if self.0.len() < N {
+            return None;
+        }
+        let (bytes, rest) = self.0.split_at(N);
+        self.0 = rest;
+        bytes.try_into().ok()
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.bytes::<1>().map(|b| b[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        self.bytes().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn f32(&mut self) -> Option<f32> {
+        self.bytes().map(f32::from_le_bytes)
+    }
+
+    fn vec2(&mut self) -> Option<Vec2> {
+        Some(Vec2::new(self.f32()?, self.f32()?))
+    }
+}
+
+/// What a client wants its player to do this frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputIntent {
+    pub movement: f32,
+    pub climb: f32,
+    /// One bit per button [`Action`], by its position in [`Action::ALL`].
+    pub buttons: u32,
+}
+
+impl InputIntent {
+    pub fn from_action_state(action_state: &ActionState) -> Self {
+        let mut buttons = 0;
+        for (bit, action) in Action::ALL.iter().enumerate() {
+            if !action.is_axis() && action_state.pressed(*action) {
+                buttons |= 1 << bit;
+            }
+        }
+        Self {
+            movement: action_state.value(Action::Move),
+            climb: action_state.value(Action::Climb),
+            buttons,
+        }
+    }
+
+    pub fn apply(&self, action_state: &mut ActionState) {
+        for (bit, action) in Action::ALL.iter().enumerate() {
+            let value = match action {
+                Action::Move => self.movement,
+                Action::Climb => self.climb,
+                _ if self.buttons & (1 << bit) != 0 => 1.0,
+                _ => 0.0,
+            };
+            action_state.set(*action, value);
+        }
+    }
+
+    pub(crate) fn write(&self, writer: &mut ByteWriter) {
+        writer.f32(self.movement);
+        writer.f32(self.climb);
+        writer.u32(self.buttons);
+    }
+
+    pub(crate) fn read(reader: &mut ByteReader) -> Option<Self> {
+        Some(Self {
+            movement: reader.f32()?.clamp(-1.0, 1.0),
+            climb: reader.f32()?.clamp(-1.0, 1.0),
+            buttons: reader.u32()?,
+        })
+    }
+}
+
+/// The replicated state of one entity.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Current and maximum health.
+    pub health: Option<(f32, f32)>,
+    pub inventory: Option<Vec<ItemStack>>,
+}
+
+/// The replicated state of the whole world, by network id.
+pub type WorldState = BTreeMap<u32, EntityState>;
+
+/// The fields of an [`EntityState`] that changed since a baseline, or that the entity is gone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EntityDelta {
+    /// The entity was despawned, none of the other fields are set.
+    pub removed: bool,
+    pub position: Option<Vec2>,
+    pub velocity: Option<Vec2>,
+    pub health: Option<(f32, f32)>,
+    pub inventory: Option<Vec<ItemStack>>,
+}
+
+impl EntityDelta {
+    /// The delta from `baseline` to `current`, `None` if nothing changed.
+    pub fn between(baseline: Option<&EntityState>, current: &EntityState) -> Option<Self> {
+        let Some(baseline) = baseline else {
+            return Some(Self {
+                removed: false,
+                position: Some(current.position),
+                velocity: Some(current.velocity),
+                health: current.health,
+                inventory: current.inventory.clone(),
+            });
+        };
+
+        let delta = Self {
+            removed: false,
+            // Compared exactly, skipping small changes would let clients drift from the baselines
+            // the host keeps.
+            position: (baseline.position != current.position).then_some(current.position),
+            velocity: (baseline.velocity != current.velocity).then_some(current.velocity),
+            health: (baseline.health != current.health)
+                .then_some(current.health)
+                .flatten(),
+            inventory: (baseline.inventory != current.inventory)
+                .then(|| current.inventory.clone())
+                .flatten(),
+        };
+        (delta != Self::default()).then_some(delta)
+    }
+
+    pub fn apply(&self, baseline: Option<&EntityState>) -> EntityState {
+        let mut state = baseline.cloned().unwrap_or_default();
+        if let Some(position) = self.position {
+            state.position = position;
+        }
+        if let Some(velocity) = self.velocity {
+            state.velocity = velocity;
+        }
+        if self.health.is_some() {
+            state.health = self.health;
+        }
+        if self.inventory.is_some() {
+            state.inventory = self.inventory.clone();
+        }
+        state
+    }
+}
+
+/// Builds the deltas needed to turn `baseline` into `current`.
+pub fn world_delta(baseline: Option<&WorldState>, current: &WorldState) -> Vec<(u32, EntityDelta)> {
+    let changed = current.iter().filter_map(|(id, state)| {
+        EntityDelta::between(baseline.and_then(|b| b.get(id)), state).map(|d| (*id, d))
+    });
+    let removed = baseline
+        .into_iter()
+        .flat_map(|baseline| baseline.keys())
+        .filter(|id| !current.contains_key(id))
+        .map(|id| {
+            let delta = EntityDelta {
+                removed: true,
+                ..default()
+            };
+            (*id, delta)
+        });
+    changed.chain(removed).collect()
+}
+
+/// Applies deltas to a copy of `baseline`.
+pub fn apply_world_delta(
+    baseline: Option<&WorldState>,
+    deltas: &[(u32, EntityDelta)],
+) -> WorldState {
+    let mut state = baseline.cloned().unwrap_or_default();
+    for (id, delta) in deltas {
+        if delta.removed {
+            state.remove(id);
+            continue;
+        }
+        let entity = delta.apply(state.get(id));
+        state.insert(*id, entity);
+    }
+    state
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    Hello {
+        version: u8,
+    },
+    Input {
+        /// The newest snapshot the client has, used as the baseline for the next delta.
+        ack: Option<u32>,
+        intent: InputIntent,
+    },
+    Goodbye,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerMessage {
+    Welcome {
+        player_index: u32,
+        player_count: u32,
+        seed: u64,
+    },
+    /// Every player slot is taken.
+    Full,
+    Snapshot {
+        tick: u32,
+        /// Host time in seconds, used to interpolate between snapshots.
+        time: f32,
+        /// The snapshot the deltas are relative to, `None` for a full snapshot.
+        baseline: Option<u32>,
+        deltas: Vec<(u32, EntityDelta)>,
+    },
+}
+
+fn write_option_tick(writer: &mut ByteWriter, tick: Option<u32>) {
+    // Tick 0 is never sent, it stands for "none".
+    writer.u32(tick.map_or(0, |tick| tick + 1));
+}
+
+fn read_option_tick(reader: &mut ByteReader) -> Option<Option<u32>> {
+    let value = reader.u32()?;
+    Some(value.checked_sub(1))
+}
+
+impl ClientMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            ClientMessage::Hello { version } => {
+                let mut writer = ByteWriter::new(HELLO);
+                writer.u8(*version);
+                writer.0
+            }
+            ClientMessage::Input { ack, intent } => {
+                let mut writer = ByteWriter::new(INPUT);
+                write_option_tick(&mut writer, *ack);
+                intent.write(&mut writer);
+                writer.0
+            }
+            ClientMessage::Goodbye => ByteWriter::new(GOODBYE).0,
+        }
+    }
+
+    /// Decodes a packet, `None` if it is malformed.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader(bytes);
+        match reader.u8()? {
+            HELLO => Some(ClientMessage::Hello {
+                version: reader.u8()?,
+            }),
+            INPUT => Some(ClientMessage::Input {
+                ack: read_option_tick(&mut reader)?,
+                intent: InputIntent::read(&mut reader)?,
+            }),
+            GOODBYE => Some(ClientMessage::Goodbye),
+            _ => None,
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            ServerMessage::Welcome {
+                player_index,
+                player_count,
+                seed,
+            } => {
+                let mut writer = ByteWriter::new(WELCOME);
+                writer.u32(*player_index);
+                writer.u32(*player_count);
+                writer.u64(*seed);
+                writer.0
+            }
+            ServerMessage::Full => ByteWriter::new(FULL).0,
+            ServerMessage::Snapshot {
+                tick,
+                time,
+                baseline,
+                deltas,
+            } => {
+                let mut writer = ByteWriter::new(SNAPSHOT);
+                writer.u32(*tick);
+                writer.f32(*time);
+                write_option_tick(&mut writer, *baseline);
+                writer.u32(deltas.len() as u32);
+                for (id, delta) in deltas {
+                    write_delta(&mut writer, *id, delta);
+                }
+                writer.0
+            }
+        }
+    }
+
+    /// Decodes a packet, `None` if it is malformed.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader(bytes);
+        match reader.u8()? {
+            WELCOME => Some(ServerMessage::Welcome {
+                player_index: reader.u32()?,
+                player_count: reader.u32()?,
+                seed: reader.u64()?,
+            }),
+            FULL => Some(ServerMessage::Full),
+            SNAPSHOT => {
+                let tick = reader.u32()?;
+                let time = reader.f32()?;
+                let baseline = read_option_tick(&mut reader)?;
+                let count = reader.u32()? as usize;
+                // Every delta takes at least 5 bytes, don't trust the count blindly.
+                if count > bytes.len() / 5 {
+                    return None;
+                }
+                let deltas = (0..count)
+                    .map(|_| read_delta(&mut reader))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(ServerMessage::Snapshot {
+                    tick,
+                    time,
+                    baseline,
+                    deltas,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn write_delta(writer: &mut ByteWriter, id: u32, delta: &EntityDelta) {
+    let mut mask = 0;
+    if delta.removed {
+        mask |= REMOVED;
+    }
+    if delta.position.is_some() {
+        mask |= HAS_POSITION;
+    }
+    if delta.velocity.is_some() {
+        mask |= HAS_VELOCITY;
+    }
+    if delta.health.is_some() {
+        mask |= HAS_HEALTH;
+    }
+    if delta.inventory.is_some() {
+        mask |= HAS_INVENTORY;
+    }
+
+    writer.u32(id);
+    writer.u8(mask);
+    if let Some(position) = delta.position {
+        writer.vec2(position);
+    }
+    if let Some(velocity) = delta.velocity {
+        writer.vec2(velocity);
+    }
+    if let Some((current, max)) = delta.health {
+        writer.f32(current);
+        writer.f32(max);
+    }
+    if let Some(inventory) = &delta.inventory {
+        writer.u8(inventory.len() as u8);
+        for stack in inventory {
+            writer.u8(stack.item as u8);
+            writer.u32(stack.count);
+        }
+    }
+}
+
+fn read_delta(reader: &mut ByteReader) -> Option<(u32, EntityDelta)> {
+    let id = reader.u32()?;
+    let mask = reader.u8()?;
+    let mut delta = EntityDelta {
+        removed: mask & REMOVED != 0,
+        ..default()
+    };
+    if mask & HAS_POSITION != 0 {
+        delta.position = Some(reader.vec2()?);
+    }
+    if mask & HAS_VELOCITY != 0 {
+        delta.velocity = Some(reader.vec2()?);
+    }
+    if mask & HAS_HEALTH != 0 {
+        delta.health = Some((reader.f32()?, reader.f32()?));
+    }
+    if mask & HAS_INVENTORY != 0 {
+        let len = reader.u8()?;
+        let stacks = (0..len)
+            .map(|_| {
+                let item = *ItemId::ALL.get(reader.u8()? as usize)?;
+                Some(ItemStack {
+                    item,
+                    count: reader.u32()?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        delta.inventory = Some(stacks);
+    }
+    Some((id, delta))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player_state(x: f32) -> EntityState {
+        EntityState {
+            position: Vec2::new(x, 10.0),
+            velocity: Vec2::new(1.0, 0.0),
+            health: Some((100.0, 110.0)),
+            inventory: Some(vec![ItemStack {
+                item: ItemId::Gasoline,
+                count: 2,
+            }]),
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips() {
+        let world = WorldState::from([(0, player_state(5.0)), (16, player_state(-3.0))]);
+        let message = ServerMessage::Snapshot {
+            tick: 42,
+            time: 1.5,
+            baseline: None,
+            deltas: world_delta(None, &world),
+        };
+        assert_eq!(ServerMessage::decode(&message.encode()), Some(message));
+    }
+
+    #[test]
+    fn delta_only_contains_changes() {
+        let baseline = WorldState::from([(0, player_state(5.0)), (1, player_state(0.0))]);
+        let mut current = baseline.clone();
+        current.get_mut(&0).unwrap().position.x = 6.0;
+
+        let deltas = world_delta(Some(&baseline), &current);
+        assert_eq!(
+            deltas,
+            vec![(
+                0,
+                EntityDelta {
+                    position: Some(Vec2::new(6.0, 10.0)),
+                    ..default()
+                }
+            )]
+        );
+        assert_eq!(apply_world_delta(Some(&baseline), &deltas), current);
+    }
+
+    #[test]
+    fn despawned_entities_are_removed() {
+        let baseline = WorldState::from([(0, player_state(5.0)), (16, player_state(0.0))]);
+        let current = WorldState::from([(0, player_state(5.0))]);
+
+        let deltas = world_delta(Some(&baseline), &current);
+        let message = ServerMessage::Snapshot {
+            tick: 2,
+            time: 0.5,
+            baseline: Some(1),
+            deltas: deltas.clone(),
+        };
+        assert_eq!(ServerMessage::decode(&message.encode()), Some(message));
+        assert_eq!(apply_world_delta(Some(&baseline), &deltas), current);
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected() {
+        let message = ClientMessage::Input {
+            ack: Some(3),
+            intent: InputIntent {
+                movement: -1.0,
+                climb: 0.0,
+                buttons: 0b10,
+            },
+        };
+        let bytes = message.encode();
+        assert_eq!(ClientMessage::decode(&bytes), Some(message));
+        assert_eq!(ClientMessage::decode(&bytes[..bytes.len() - 1]), None);
+    }
+}
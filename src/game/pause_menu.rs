@@ -0,0 +1,241 @@
+//! The pause menu, opened by a local player's Pause and the only way to save and quit a run.
+
+use bevy::prelude::*;
+
+use super::input::{Action, ActionState, RemoteInput};
+use super::menu::{screen_root, text_style, MenuInput};
+use super::player::Player;
+use super::save::save_and_quit;
+use crate::{AppState, GameFont};
+
+#[derive(Component)]
+struct OnPauseScreen;
+
+/// The text of one [`PauseRow`], by index.
+#[derive(Component)]
+struct PauseRowText(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PauseRow {
+    Resume,
+    SaveAndQuit,
+}
+
+const PAUSE_ROWS: [PauseRow; 2] = [PauseRow::Resume, PauseRow::SaveAndQuit];
+
+fn row_label(row: PauseRow) -> &'static str {
+    match row {
+        PauseRow::Resume => "Resume",
+        PauseRow::SaveAndQuit => "Save and quit",
+    }
+}
+
+/// The selected row, present while the game is paused.
+#[derive(Resource, Default)]
+pub struct PauseMenu {
+    row: usize,
+}
+
+/// Whether a local player just pressed Pause.
+///
+/// Reads the players' own [`ActionState`]s, the merged resource would let any device pause and
+/// remote players must not pause the host.
+fn pause_pressed(players: Query<&ActionState, (With<Player>, Without<RemoteInput>)>) -> bool {
+    players
+        .iter()
+        .any(|action_state| action_state.just_pressed(Action::Pause))
+}
+
+fn open_pause_menu(
+    mut commands: Commands,
+    game_font: Res<GameFont>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    time.pause();
+    commands.init_resource::<PauseMenu>();
+    commands
+        .spawn((
+            Name::new("PauseMenu"),
+            OnPauseScreen,
+            screen_root(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Paused",
+                text_style(&game_font, 48.0, Color::WHITE),
+            ));
+            for (index, row) in PAUSE_ROWS.into_iter().enumerate() {
+                parent.spawn((
+                    PauseRowText(index),
+                    TextBundle::from_section(
+                        row_label(row),
+                        text_style(&game_font, 24.0, Color::WHITE),
+                    ),
+                ));
+            }
+            parent.spawn(TextBundle::from_section(
+                "Up/Down to select, Enter (A) to choose, Escape (B) to resume",
+                text_style(&game_font, 16.0, Color::GRAY),
+            ));
+        });
+}
+
+/// Closes the menu and lets time run again.
+fn close_pause_menu(world: &mut World) {
+    world.resource_mut::<Time<Virtual>>().unpause();
+    world.remove_resource::<PauseMenu>();
+    let screens: Vec<Entity> = world
+        .query_filtered::<Entity, With<OnPauseScreen>>()
+        .iter(world)
+        .collect();
+    for entity in screens {
+        world.entity_mut(entity).despawn_recursive();
+    }
+}
+
+fn pause_menu_input(
+    mut commands: Commands,
+    menu_input: MenuInput,
+    players: Query<&ActionState, (With<Player>, Without<RemoteInput>)>,
+    mut menu: ResMut<PauseMenu>,
+) {
+    // The press that opened the menu is still down this frame, and Escape is also back.
+    if menu.is_added() {
+        return;
+    }
+    if menu_input.back() || pause_pressed(players) {
+        commands.add(close_pause_menu);
+        return;
+    }
+
+    if menu_input.down() {
+        menu.row = (menu.row + 1) % PAUSE_ROWS.len();
+    }
+    if menu_input.up() {
+        menu.row = (menu.row + PAUSE_ROWS.len() - 1) % PAUSE_ROWS.len();
+    }
+
+    if menu_input.confirm() {
+        match PAUSE_ROWS[menu.row] {
+            PauseRow::Resume => commands.add(close_pause_menu),
+            // Leaving the run closes the menu too.
+            PauseRow::SaveAndQuit => commands.add(save_and_quit),
+        }
+    }
+}
+
+fn update_pause_text(menu: Res<PauseMenu>, mut texts: Query<(&PauseRowText, &mut Text)>) {
+    if !menu.is_changed() {
+        return;
+    }
+
+    for (row_text, mut text) in &mut texts {
+        text.sections[0].style.color = if row_text.0 == menu.row {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                open_pause_menu.run_if(not(resource_exists::<PauseMenu>()).and_then(pause_pressed)),
+                (pause_menu_input, update_pause_text)
+                    .chain()
+                    .distributive_run_if(resource_exists::<PauseMenu>()),
+            )
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(OnExit(AppState::InGame), close_pause_menu);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{keyboard::KeyboardInput, ButtonState, InputPlugin};
+
+    use super::*;
+
+    fn app() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, PauseMenuPlugin))
+            .add_state::<AppState>()
+            .init_resource::<ActionState>()
+            .insert_resource(GameFont(Handle::default()));
+        app.world
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+        app.update();
+        let player = app.world.spawn((Player, ActionState::default())).id();
+        (app, player)
+    }
+
+    fn press_pause(app: &mut App, player: Entity, pressed: bool) {
+        let value = if pressed { 1.0 } else { 0.0 };
+        let mut action_state = app.world.get_mut::<ActionState>(player).unwrap();
+        action_state.set(Action::Pause, value);
+        app.update();
+    }
+
+    fn press_key(app: &mut App, key_code: KeyCode) {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state: ButtonState::Pressed,
+            window: Entity::PLACEHOLDER,
+        });
+        app.update();
+    }
+
+    fn paused(app: &App) -> bool {
+        app.world.contains_resource::<PauseMenu>()
+            && app.world.resource::<Time<Virtual>>().is_paused()
+    }
+
+    #[test]
+    fn pause_opens_the_menu_without_leaving_the_run() {
+        let (mut app, player) = app();
+
+        // Only a player's own input pauses, not the devices merged into the resource.
+        app.world
+            .resource_mut::<ActionState>()
+            .set(Action::Pause, 1.0);
+        app.update();
+        assert!(!paused(&app));
+
+        press_pause(&mut app, player, true);
+        assert!(paused(&app));
+        press_pause(&mut app, player, false);
+        assert!(paused(&app));
+        assert_eq!(
+            app.world.resource::<State<AppState>>().get(),
+            &AppState::InGame
+        );
+
+        // "Resume" is selected first.
+        press_key(&mut app, KeyCode::Return);
+        assert!(!paused(&app));
+        assert!(!app.world.resource::<Time<Virtual>>().is_paused());
+        assert_eq!(
+            app.world.resource::<State<AppState>>().get(),
+            &AppState::InGame
+        );
+    }
+
+    #[test]
+    fn pause_again_resumes() {
+        let (mut app, player) = app();
+        press_pause(&mut app, player, true);
+        press_pause(&mut app, player, false);
+        press_pause(&mut app, player, true);
+        assert!(!app.world.contains_resource::<PauseMenu>());
+        assert!(!app.world.resource::<Time<Virtual>>().is_paused());
+    }
+}
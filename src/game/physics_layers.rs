@@ -7,4 +7,11 @@ pub enum Layer {
     Climbable,
     Interactable,
     Ground,
+    PlayerProjectile,
+    EnemyProjectile,
+    PlayerHitbox,
+    EnemyHitbox,
+    PlayerHurtbox,
+    EnemyHurtbox,
+    GravityZone,
 }
@@ -1,29 +1,35 @@
 use crate::engine::animation::{AnimationIndices, AnimationTimer};
 
-use bevy::{
-    math::*,
-    prelude::*,
-    transform::{commands, TransformSystem},
-};
+use bevy::{ecs::query::Has, math::*, prelude::*, transform::commands};
 use bevy_xpbd_2d::math::*;
 use bevy_xpbd_2d::plugins::spatial_query::ShapeCaster;
 use bevy_xpbd_2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
+use super::{
+    combat::{Barrier, Dead, Health, IFramesOnHit, Team},
+    floating_text::FloatingTextBundle,
+    hitbox::HurtboxBundle,
+    hud::HudTarget,
+    input::{ActionState, InputDevices},
+    items::Inventory,
+    net::{AuthoritySet, NetId},
+    skills::{Skill, Skills},
+    util::RunEntity,
+};
 use super::{physics_layers::Layer, player_controller::CharacterControllerPlugin};
 use super::{player_controller::CharacterControllerBundle, stats::*};
-use crate::{GameFont, Ground};
+use crate::{AppState, GameFont, Ground, GRAVITY};
 
+/// An event sent when a player gains a level.
 #[derive(Event)]
-struct LevelUpEvent(Entity);
-
-#[derive(Component)]
-struct LevelUpText;
-
-#[derive(Component, Deref, DerefMut)]
-pub struct LevelUpTextTimer(pub Timer);
+pub struct LevelUpEvent(pub Entity);
 
 const LVL_TEXT_HEIGHT_OFFSET: f32 = 10.0;
 
+/// The XP needed to reach the next level.
+pub const LEVEL_UP_XP: i32 = 2;
+
 fn reset_player_xp_level(
     mut ev_levelup: EventReader<LevelUpEvent>,
     mut query: Query<(&mut PlayerXp, &mut PlayerLevel)>,
@@ -36,82 +42,58 @@ fn reset_player_xp_level(
     }
 }
 
-fn destroy_levelup_text(
-    time: Res<Time>,
-    mut commands: Commands,
-    mut query: Query<(Entity, &mut LevelUpTextTimer)>,
-) {
-    for (entity, mut timer) in &mut query {
-        timer.tick(time.delta());
-        if timer.just_finished() {
-            commands.entity(entity).despawn();
-        }
-    }
-}
-
-fn levelup_text_follow(
-    mut text_query: Query<&mut Transform, With<LevelUpText>>,
-    player_query: Query<&Position, With<Player>>,
-) {
-    for mut text_pos in &mut text_query {
-        for player_pos in &player_query {
-            text_pos.translation.x = player_pos.x;
-            text_pos.translation.y = player_pos.y + LVL_TEXT_HEIGHT_OFFSET;
-        }
-    }
-}
-
 fn spawn_levelup_text(
     mut commands: Commands,
     game_font: Res<GameFont>,
     mut ev_levelup: EventReader<LevelUpEvent>,
     query: Query<&Position, With<Player>>,
 ) {
-    let text_style = TextStyle {
-        font: game_font.0.clone(),
-        font_size: 8.0,
-        color: Color::YELLOW,
-    };
-
-    for _ev in ev_levelup.read() {
-        let player_pos = query.get_single().unwrap();
-        commands.spawn((
-            LevelUpText,
-            Text2dBundle {
-                text: Text::from_section("Level Up!", text_style.clone())
-                    .with_alignment(TextAlignment::Center),
-                transform: Transform::from_xyz(
-                    player_pos.x,
-                    player_pos.y + LVL_TEXT_HEIGHT_OFFSET,
-                    0.,
-                )
-                .with_scale(Vec3::new(0.5, 0.5, 1.0)),
-                ..default()
-            },
-            LevelUpTextTimer(Timer::from_seconds(1., TimerMode::Once)),
-        ));
+    for ev in ev_levelup.read() {
+        let Ok(player_pos) = query.get(ev.0) else {
+            continue;
+        };
+        commands.spawn(
+            FloatingTextBundle::new("Level Up!", 4.0, Color::YELLOW, &game_font, player_pos.0)
+                .following(ev.0, Vec2::Y * LVL_TEXT_HEIGHT_OFFSET)
+                .with_lifetime(1.0),
+        );
     }
 }
 
 fn player_level_up(mut ev_levelup: EventWriter<LevelUpEvent>, query: Query<(Entity, &PlayerXp)>) {
     for (entity, xp) in query.iter() {
-        if xp.0 > 1 {
+        if xp.0 >= LEVEL_UP_XP {
             ev_levelup.send(LevelUpEvent(entity));
         }
     }
 }
 
 #[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct PlayerXp(pub i32);
 
 #[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct PlayerLevel(pub i32);
 
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct Gold(pub u32);
+
+fn commando_skills() -> Skills {
+    Skills::new(
+        Skill::new("Double Tap", 0.15, 1),
+        Skill::new("Phase Round", 3.0, 1),
+        Skill::new("Tactical Dive", 4.0, 1),
+        Skill::new("Suppressive Fire", 9.0, 1),
+    )
+}
+
 #[derive(Bundle)]
 struct PlayerStatBundle {
     // xp: PlayerXp,
     speed: SpeedStat,
-    // health: HealthStat,
+    health: Health,
     // jump_height: JumpHeightStat,
     jumps: JumpsStat,
 }
@@ -120,6 +102,7 @@ impl PlayerStatBundle {
     pub fn new() -> Self {
         Self {
             speed: SpeedStat(40.0),
+            health: Health::new(110.0),
             jumps: JumpsStat::new(1, 120.0),
         }
     }
@@ -128,8 +111,66 @@ impl PlayerStatBundle {
 #[derive(Component)]
 pub struct Player;
 
+/// The character a player is playing as.
+#[derive(
+    Component,
+    Reflect,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[reflect(Component)]
+pub enum Survivor {
+    #[default]
+    Commando,
+}
+
+impl Survivor {
+    pub fn name(self) -> &'static str {
+        match self {
+            Survivor::Commando => "Commando",
+        }
+    }
+}
+
+pub const MAX_PLAYERS: u32 = 4;
+const PLAYER_SPAWN_SPACING: f32 = 10.0;
+/// Tints telling local players apart until survivors have their own sprites.
+const PLAYER_COLORS: [Color; MAX_PLAYERS as usize] = [
+    Color::WHITE,
+    Color::rgb(0.6, 0.8, 1.0),
+    Color::rgb(1.0, 0.7, 0.6),
+    Color::rgb(0.7, 1.0, 0.6),
+];
+
+/// The number of players in the run, chosen in the main menu.
+///
+/// Anything that scales with the party size, like enemy health, should read this rather than
+/// counting `Player` entities, since dead players still count.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct PlayerCount(pub u32);
+
+impl Default for PlayerCount {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// Which local player an entity is, starting at 0.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub struct PlayerIndex(pub u32);
+
 pub fn spawn_player(
     mut commands: Commands,
+    player_count: Res<PlayerCount>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
 ) {
@@ -145,24 +186,63 @@ pub fn spawn_player(
     let texture_atlas_handle = texture_atlases.add(texture_atlas);
     let animation_indices = AnimationIndices { first: 1, last: 7 };
 
-    commands.spawn((
-        Name::new("Player"),
-        SpriteSheetBundle {
-            texture_atlas: texture_atlas_handle,
-            sprite: TextureAtlasSprite::new(animation_indices.first),
-            transform: Transform::from_scale(Vec3::splat(1.0)),
-            ..default()
-        },
-        animation_indices,
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        PlayerXp::default(),
-        PlayerLevel::default(),
-        PlayerStatBundle::new(),
-        // PlayerCollisionBundle::new(),
-        CharacterControllerBundle::new(Collider::cuboid(6.0, 11.0), Vector::NEG_Y * 1000.0)
-            .with_movement(220.0, 0.85, 220.0, 1, (30.0 as Scalar).to_radians()),
-        Player,
-    ));
+    for index in 0..player_count.0 {
+        let mut sprite = TextureAtlasSprite::new(animation_indices.first);
+        sprite.color = PLAYER_COLORS[index as usize % PLAYER_COLORS.len()];
+
+        let mut player = commands.spawn((
+            Name::new(format!("Player {}", index + 1)),
+            SpriteSheetBundle {
+                texture_atlas: texture_atlas_handle.clone(),
+                sprite,
+                transform: Transform::from_xyz(index as f32 * PLAYER_SPAWN_SPACING, 0.0, 0.0),
+                ..default()
+            },
+            animation_indices.clone(),
+            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            PlayerXp::default(),
+            PlayerLevel::default(),
+            PlayerStatBundle::new(),
+            (
+                Survivor::Commando,
+                Barrier::default(),
+                Gold::default(),
+                commando_skills(),
+            ),
+            Inventory::default(),
+            (
+                PlayerIndex(index),
+                NetId(index),
+                // Only the first player shares the keyboard, everyone else needs a gamepad.
+                InputDevices {
+                    keyboard_mouse: index == 0,
+                    gamepad: None,
+                },
+                ActionState::default(),
+            ),
+            RunEntity,
+            Team::Player,
+            IFramesOnHit(0.75),
+            // PlayerCollisionBundle::new(),
+            CharacterControllerBundle::new(Collider::cuboid(6.0, 11.0), GRAVITY).with_movement(
+                220.0,
+                0.85,
+                220.0,
+                1,
+                (30.0 as Scalar).to_radians(),
+            ),
+            Player,
+        ));
+        player.with_children(|parent| {
+            parent.spawn((
+                Name::new("Hurtbox"),
+                HurtboxBundle::new(Team::Player, Collider::cuboid(6.0, 11.0)),
+            ));
+        });
+        if index == 0 {
+            player.insert(HudTarget);
+        }
+    }
 }
 
 pub fn animate_player(
@@ -196,25 +276,13 @@ pub fn animate_player(
     }
 }
 
-fn add_level(keyboard_input: Res<Input<KeyCode>>, mut player: Query<&mut PlayerXp, With<Player>>) {
-    for mut player_xp in &mut player {
-        if keyboard_input.any_just_pressed([KeyCode::L]) {
-            player_xp.0 += 2;
-        }
-    }
-}
-
-pub fn camera_follow(
-    mut camera_pos: Query<&mut Transform, (Without<Player>, With<Camera>)>,
-    player_pos: Query<&Transform, With<Player>>,
+/// Ends the run once every player is dead.
+fn players_dead(
+    players: Query<Has<Dead>, With<Player>>,
+    mut next_state: ResMut<NextState<AppState>>,
 ) {
-    for player_transform in &player_pos {
-        let mut camera_transform = camera_pos.get_single_mut().unwrap();
-        camera_transform.translation = camera_transform
-            .translation
-            .truncate()
-            .lerp(player_transform.translation.truncate(), 0.1)
-            .extend(0.0);
+    if !players.is_empty() && players.iter().all(|is_dead| is_dead) {
+        next_state.set(AppState::GameOver);
     }
 }
 
@@ -223,15 +291,18 @@ pub struct PlayerPlugin;
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<PlayerLevel>()
+            .register_type::<PlayerIndex>()
+            .init_resource::<PlayerCount>()
             .register_type::<PlayerXp>()
+            .register_type::<Gold>()
+            .register_type::<Survivor>()
             .add_event::<LevelUpEvent>()
             .add_plugins(CharacterControllerPlugin)
-            .add_systems(Startup, spawn_player)
+            .add_systems(OnEnter(AppState::InGame), spawn_player)
             .add_systems(
                 Update,
                 (
                     animate_player,
-                    add_level,
                     (
                         player_level_up,
                         reset_player_xp_level.run_if(on_event::<LevelUpEvent>()),
@@ -239,18 +310,9 @@ impl Plugin for PlayerPlugin {
                         // detect_grounded,
                     )
                         .chain(),
-                    destroy_levelup_text,
-                ),
-            )
-            .add_systems(
-                PostUpdate,
-                (
-                    camera_follow
-                        .after(PhysicsSet::Sync)
-                        .before(TransformSystem::TransformPropagate),
-                    levelup_text_follow
-                        .after(PhysicsSet::Sync)
-                        .before(TransformSystem::TransformPropagate),
+                    players_dead
+                        .run_if(in_state(AppState::InGame))
+                        .in_set(AuthoritySet),
                 ),
             );
     }
@@ -1,19 +1,42 @@
-use bevy::{ecs::query::Has, prelude::*};
+use bevy::{ecs::query::Has, prelude::*, transform::TransformSystem, utils::HashMap};
 use bevy_xpbd_2d::{math::*, prelude::*, SubstepSchedule, SubstepSet};
 
+use super::combat::{ApplyDamageSet, Dead, KnockbackEvent};
 use super::enemy::dummy::Layer;
+use super::gravity_zone::GravityZone;
+use super::input::{Action, ActionState, ActionStateSet};
+use crate::AppState;
+
+/// How long knockback overrides [`MovementDampingFactor`] for.
+pub const KNOCKBACK_RECOVERY_SECS: f32 = 0.25;
 
 pub struct CharacterControllerPlugin;
 
 impl Plugin for CharacterControllerPlugin {
     fn build(&self, app: &mut App) {
+        // The controller runs at the physics rate in `FixedUpdate`, so movement doesn't depend
+        // on the frame rate. Input is latched every frame until a fixed step consumes it, except
+        // while time is paused so presses in the pause menu don't carry over into the game.
         app.register_type::<JumpCount>()
-            .add_event::<MovementAction>()
+            .register_type::<MovementAcceleration>()
+            .register_type::<JumpImpulse>()
+            .add_event::<MovementEvent>()
+            .add_event::<JumpEvent>()
+            .add_systems(
+                PreUpdate,
+                latch_input
+                    .after(ActionStateSet)
+                    .run_if(in_state(AppState::InGame).and_then(time_running)),
+            )
+            .add_systems(Update, latch_knockback.after(ApplyDamageSet))
             .add_systems(
-                Update,
+                FixedUpdate,
                 (
-                    keyboard_input,
-                    gamepad_input,
+                    store_previous_positions,
+                    action_input,
+                    tick_hit_reactions,
+                    apply_knockback,
+                    update_gravity,
                     update_grounded,
                     check_can_climb,
                     update_climbing,
@@ -22,7 +45,16 @@ impl Plugin for CharacterControllerPlugin {
                     movement,
                     apply_movement_damping,
                 )
-                    .chain(),
+                    .chain()
+                    .before(PhysicsSet::Prepare)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(FixedUpdate, store_current_positions.after(PhysicsSet::Sync))
+            .add_systems(
+                PostUpdate,
+                interpolate_transforms
+                    .in_set(InterpolateSet)
+                    .after(TransformSystem::TransformPropagate),
             )
             .add_systems(
                 // Run collision handling in substep schedule
@@ -32,8 +64,20 @@ impl Plugin for CharacterControllerPlugin {
     }
 }
 
-/// An event sent for a movement input action.
+/// An event sent for a movement input action of a character controller.
 #[derive(Event)]
+pub struct MovementEvent {
+    pub entity: Entity,
+    pub action: MovementAction,
+}
+
+/// An event sent when a character controller jumps.
+#[derive(Event)]
+pub struct JumpEvent {
+    pub entity: Entity,
+}
+
+/// A movement input action.
 pub enum MovementAction {
     Move(Scalar),
     Jump,
@@ -59,19 +103,65 @@ pub struct CanClimb;
 #[component(storage = "SparseSet")]
 pub struct Climbing;
 
-/// The acceleration used for character movement.
+/// Flies through walls without gravity, the climb input moves up and down. Toggled from the dev
+/// console, holds the collision layers to restore afterwards.
+#[derive(Component)]
+pub struct Noclip(pub CollisionLayers);
+
+/// A component indicating that an entity was knocked back recently.
+/// Movement damping is suspended so the knockback isn't eaten up on the next frame.
+#[derive(Component, Deref, DerefMut)]
+pub struct KnockbackRecovery(pub Timer);
+
+/// A component indicating that an entity was hit hard enough to ignore all movement input.
+#[derive(Component, Deref, DerefMut)]
+pub struct Hitstun(pub Timer);
+
+/// Button presses seen since the last fixed step.
+///
+/// A frame can run zero or several fixed steps, so reading `just_pressed` from inside a step
+/// would drop or repeat presses.
+#[derive(Component, Default)]
+pub struct InputLatch {
+    jump: bool,
+}
+
+/// Knockback and hitstun that landed since the last fixed step.
+///
+/// Damage is dealt once per frame and a frame can run zero or several fixed steps, like
+/// [`InputLatch`] this keeps hit reactions from depending on how long events are kept around.
 #[derive(Component)]
-pub struct MovementAcceleration(Scalar);
+pub struct PendingKnockback {
+    knockback: Vector,
+    hitstun: f32,
+}
+
+/// The physics positions of the last two fixed steps.
+///
+/// The body is drawn between the two by how far the frame is into the next step, so motion
+/// stays smooth when the frame rate doesn't match the physics rate.
+#[derive(Component, Default)]
+pub struct PhysicsInterpolation {
+    previous: Option<Vector>,
+    current: Option<Vector>,
+}
+
+/// The acceleration used for character movement.
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct MovementAcceleration(pub Scalar);
 
 /// The damping factor used for slowing down movement.
 #[derive(Component)]
 pub struct MovementDampingFactor(Scalar);
 
 /// The strength of a jump.
-#[derive(Component)]
-pub struct JumpImpulse(Scalar);
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
+pub struct JumpImpulse(pub Scalar);
 
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct JumpCount {
     pub current: u32,
     pub max: u32,
@@ -85,7 +175,45 @@ impl JumpCount {
 
 /// The gravitational acceleration used for a character controller.
 #[derive(Component)]
-pub struct ControllerGravity(Vector);
+pub struct ControllerGravity(pub Vector);
+
+/// The gravity acting on a character controller this step: its [`ControllerGravity`] changed by
+/// the [`GravityZone`]s it is in.
+///
+/// Jumping, walking and grounding are relative to `up`, so a controller can stand on ceilings
+/// and walls. Stages are built on a grid, so `up` is snapped to the nearest axis and a sideways
+/// wind doesn't tilt the controller.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct EffectiveGravity {
+    pub acceleration: Vector,
+    /// Away from the ground, kept as it was while there is no gravity.
+    pub up: Vector,
+}
+
+impl EffectiveGravity {
+    pub fn new(acceleration: Vector) -> Self {
+        Self {
+            acceleration,
+            up: up_axis(acceleration).unwrap_or(Vector::Y),
+        }
+    }
+
+    /// The axis to walk along, positive to the right or up on screen.
+    pub fn forward(&self) -> Vector {
+        Vector::new(self.up.y.abs(), self.up.x.abs())
+    }
+}
+
+/// The axis pointing against `gravity`, `None` without gravity.
+fn up_axis(gravity: Vector) -> Option<Vector> {
+    if gravity == Vector::ZERO {
+        None
+    } else if gravity.x.abs() > gravity.y.abs() {
+        Some(Vector::new(-gravity.x.signum(), 0.0))
+    } else {
+        Some(Vector::new(0.0, -gravity.y.signum()))
+    }
+}
 
 /// The maximum angle a slope can have for a character controller
 /// to be able to climb and jump. If the slope is steeper than this angle,
@@ -103,7 +231,10 @@ pub struct CharacterControllerBundle {
     col_layers: CollisionLayers,
     ground_caster: ShapeCaster,
     gravity: ControllerGravity,
+    effective_gravity: EffectiveGravity,
     movement: MovementBundle,
+    input_latch: InputLatch,
+    interpolation: PhysicsInterpolation,
     // sleeping: SleepingDisabled,
 }
 
@@ -151,13 +282,19 @@ impl CharacterControllerBundle {
             character_controller: CharacterController,
             rigid_body: RigidBody::Kinematic,
             collider,
-            col_layers: CollisionLayers::new([Layer::Player], [Layer::Ground]),
+            col_layers: CollisionLayers::new(
+                [Layer::Player],
+                [Layer::Ground, Layer::EnemyProjectile],
+            ),
             ground_caster: ShapeCaster::new(caster_shape, Vector::ZERO, 0.0, Vector::NEG_Y)
                 .with_max_time_of_impact(0.2)
                 .with_max_hits(1)
                 .with_query_filter(SpatialQueryFilter::new().with_masks([Layer::Ground])),
             gravity: ControllerGravity(gravity),
+            effective_gravity: EffectiveGravity::new(gravity),
             movement: MovementBundle::default(),
+            input_latch: InputLatch::default(),
+            interpolation: PhysicsInterpolation::default(),
             // sleeping: SleepingDisabled,
         }
     }
@@ -181,88 +318,73 @@ impl CharacterControllerBundle {
     }
 }
 
-/// Sends [`MovementAction`] events based on keyboard input.
-fn keyboard_input(
-    mut movement_event_writer: EventWriter<MovementAction>,
-    keyboard_input: Res<Input<KeyCode>>,
-) {
-    let left = keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]);
-    let right = keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]);
-
-    let horizontal = right as i8 - left as i8;
-    let h_direction = horizontal as Scalar;
-
-    if h_direction != 0.0 {
-        movement_event_writer.send(MovementAction::Move(h_direction));
-    }
-
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        movement_event_writer.send(MovementAction::Jump);
-    }
-
-    let up = keyboard_input.any_pressed([KeyCode::W, KeyCode::Up]);
-    let down = keyboard_input.any_pressed([KeyCode::S, KeyCode::Down]);
-
-    let vertical = up as i8 - down as i8;
-    let v_direction = vertical as Scalar;
+fn time_running(time: Res<Time<Virtual>>) -> bool {
+    !time.is_paused()
+}
 
-    if v_direction != 0.0 {
-        movement_event_writer.send(MovementAction::Climb(v_direction));
+fn latch_input(mut query: Query<(&ActionState, &mut InputLatch)>) {
+    for (action_state, mut latch) in &mut query {
+        if action_state.just_pressed(Action::Jump) {
+            latch.jump = true;
+        }
     }
 }
 
-/// Sends [`MovementAction`] events based on gamepad input.
-fn gamepad_input(
-    mut movement_event_writer: EventWriter<MovementAction>,
-    gamepads: Res<Gamepads>,
-    axes: Res<Axis<GamepadAxis>>,
-    buttons: Res<Input<GamepadButton>>,
+/// Sends [`MovementEvent`]s based on the [`ActionState`] of each living character controller.
+fn action_input(
+    mut movement_event_writer: EventWriter<MovementEvent>,
+    mut query: Query<
+        (Entity, &ActionState, &mut InputLatch),
+        (With<CharacterController>, Without<Dead>),
+    >,
 ) {
-    for gamepad in gamepads.iter() {
-        let axis_lx = GamepadAxis {
-            gamepad,
-            axis_type: GamepadAxisType::LeftStickX,
-        };
+    for (entity, action_state, mut latch) in &mut query {
+        let mut send = |action| movement_event_writer.send(MovementEvent { entity, action });
 
-        if let Some(x) = axes.get(axis_lx) {
-            movement_event_writer.send(MovementAction::Move(x as Scalar));
+        let h_direction = action_state.value(Action::Move) as Scalar;
+        if h_direction != 0.0 {
+            send(MovementAction::Move(h_direction));
         }
 
-        let jump_button = GamepadButton {
-            gamepad,
-            button_type: GamepadButtonType::South,
-        };
+        if std::mem::take(&mut latch.jump) {
+            send(MovementAction::Jump);
+        }
 
-        if buttons.just_pressed(jump_button) {
-            movement_event_writer.send(MovementAction::Jump);
+        let v_direction = action_state.value(Action::Climb) as Scalar;
+        if v_direction != 0.0 {
+            send(MovementAction::Climb(v_direction));
         }
     }
 }
 
 fn update_climbing(
     mut commands: Commands,
-    mut movement_event_reader: EventReader<MovementAction>,
-    query: Query<(Entity, Has<CanClimb>, Has<Climbing>), With<CharacterController>>,
+    mut movement_event_reader: EventReader<MovementEvent>,
+    query: Query<(Has<CanClimb>, Has<Climbing>, Has<Hitstun>), With<CharacterController>>,
 ) {
     for event in movement_event_reader.read() {
-        for (entity, can_climb, is_climbing) in &query {
-            let mut should_climb = is_climbing;
-            match event {
-                MovementAction::Climb(_) => {
-                    should_climb = true;
-                }
-                MovementAction::Jump => {
-                    should_climb = false;
-                }
-                _ => {}
+        let Ok((can_climb, is_climbing, is_stunned)) = query.get(event.entity) else {
+            continue;
+        };
+        if is_stunned {
+            continue;
+        }
+        let mut should_climb = is_climbing;
+        match event.action {
+            MovementAction::Climb(_) => {
+                should_climb = true;
             }
-            if can_climb && should_climb {
-                commands.entity(entity).insert(Climbing);
-                commands.entity(entity).insert(Sensor);
-            } else {
-                commands.entity(entity).remove::<Climbing>();
-                commands.entity(entity).remove::<Sensor>();
+            MovementAction::Jump => {
+                should_climb = false;
             }
+            _ => {}
+        }
+        if can_climb && should_climb {
+            commands.entity(event.entity).insert(Climbing);
+            commands.entity(event.entity).insert(Sensor);
+        } else {
+            commands.entity(event.entity).remove::<Climbing>();
+            commands.entity(event.entity).remove::<Sensor>();
         }
     }
 }
@@ -272,7 +394,6 @@ fn check_can_climb(
     spatial_query: SpatialQuery,
     query: Query<(Entity, &Collider, &Position, Has<Climbing>), With<CharacterController>>,
 ) {
-    let mut can_climb = false;
     for (entity, collider, position, is_climbing) in &query {
         let intersections = spatial_query.shape_intersections(
             &collider,                                                // Shape
@@ -281,10 +402,8 @@ fn check_can_climb(
             SpatialQueryFilter::new().with_masks([Layer::Climbable]), // Query filter
         );
 
-        if let Some(_) = intersections.first() {
-            can_climb = true;
-        }
-
+        // Each controller only climbs what it overlaps itself.
+        let can_climb = !intersections.is_empty();
         if can_climb {
             commands.entity(entity).insert(CanClimb);
         } else {
@@ -295,6 +414,46 @@ fn check_can_climb(
     }
 }
 
+/// Updates the [`EffectiveGravity`] from the [`GravityZone`]s each controller overlaps, and
+/// points the ground caster down along it.
+fn update_gravity(
+    spatial_query: SpatialQuery,
+    zones: Query<&GravityZone>,
+    mut controllers: Query<
+        (
+            &Collider,
+            &Position,
+            &ControllerGravity,
+            &mut EffectiveGravity,
+            &mut ShapeCaster,
+        ),
+        With<CharacterController>,
+    >,
+) {
+    for (collider, position, gravity, mut effective_gravity, mut ground_caster) in &mut controllers
+    {
+        let inside = spatial_query.shape_intersections(
+            collider,
+            position.0,
+            0.0,
+            SpatialQueryFilter::new().with_masks([Layer::GravityZone]),
+        );
+        let acceleration = GravityZone::combine(
+            gravity.0,
+            inside
+                .iter()
+                .filter_map(|&zone| zones.get(zone).ok())
+                .copied(),
+        );
+
+        effective_gravity.acceleration = acceleration;
+        if let Some(up) = up_axis(acceleration) {
+            effective_gravity.up = up;
+        }
+        ground_caster.direction = -effective_gravity.up;
+    }
+}
+
 //TODO: Jump count should have its own system?
 /// Updates the [`Grounded`] status for character controllers.
 fn update_grounded(
@@ -305,17 +464,22 @@ fn update_grounded(
             &mut JumpCount,
             &ShapeHits,
             &Rotation,
+            &EffectiveGravity,
             Option<&MaxSlopeAngle>,
         ),
         With<CharacterController>,
     >,
 ) {
-    for (entity, mut jump_count, hits, rotation, max_slope_angle) in &mut query {
+    for (entity, mut jump_count, hits, rotation, gravity, max_slope_angle) in &mut query {
         // The character is grounded if the shape caster has a hit with a normal
         // that isn't too steep.
         let is_grounded = hits.iter().any(|hit| {
             if let Some(angle) = max_slope_angle {
-                rotation.rotate(-hit.normal2).angle_between(Vector::Y).abs() <= angle.0
+                rotation
+                    .rotate(-hit.normal2)
+                    .angle_between(gravity.up)
+                    .abs()
+                    <= angle.0
             } else {
                 true
             }
@@ -330,63 +494,89 @@ fn update_grounded(
     }
 }
 
-/// Responds to [`MovementAction`] events and moves character controllers accordingly.
+/// Responds to [`MovementEvent`]s and moves character controllers accordingly. The dead don't
+/// move, even if they died after sending this step's events.
 fn movement(
     time: Res<Time>,
-    mut movement_event_reader: EventReader<MovementAction>,
-    mut controllers: Query<(
-        &MovementAcceleration,
-        &JumpImpulse,
-        &mut JumpCount,
-        &mut LinearVelocity,
-        &mut Position,
-        Has<Grounded>,
-        Has<Climbing>,
-    )>,
+    mut movement_event_reader: EventReader<MovementEvent>,
+    mut jump_events: EventWriter<JumpEvent>,
+    mut controllers: Query<
+        (
+            &MovementAcceleration,
+            &JumpImpulse,
+            &EffectiveGravity,
+            &mut JumpCount,
+            &mut LinearVelocity,
+            &mut Position,
+            Has<Grounded>,
+            Has<Climbing>,
+            Has<Hitstun>,
+            Has<Noclip>,
+        ),
+        Without<Dead>,
+    >,
 ) {
     // Precision is adjusted so that the example works with
     // both the `f32` and `f64` features. Otherwise you don't need this.
     let delta_time = time.delta_seconds_f64().adjust_precision();
 
     for event in movement_event_reader.read() {
-        for (
+        let Ok((
             movement_acceleration,
             jump_impulse,
+            gravity,
             mut jump_count,
             mut linear_velocity,
             mut position,
             is_grounded,
             is_climbing,
-        ) in &mut controllers
-        {
-            match event {
-                MovementAction::Move(direction) => {
-                    if !is_climbing {
-                        linear_velocity.x += *direction * movement_acceleration.0 * delta_time;
-                    }
+            is_stunned,
+            is_noclip,
+        )) = controllers.get_mut(event.entity)
+        else {
+            continue;
+        };
+        if is_stunned {
+            continue;
+        }
+        match event.action {
+            MovementAction::Move(direction) => {
+                if !is_climbing {
+                    linear_velocity.0 +=
+                        gravity.forward() * direction * movement_acceleration.0 * delta_time;
                 }
-                MovementAction::Jump => {
-                    if is_grounded || is_climbing || jump_count.current < jump_count.max {
-                        linear_velocity.y = jump_impulse.0;
-                        jump_count.current += 1;
-                    }
+            }
+            MovementAction::Jump => {
+                if is_grounded || is_climbing || jump_count.current < jump_count.max {
+                    // Only the speed along `up` is replaced, like setting `y` with normal gravity.
+                    let up_speed = linear_velocity.dot(gravity.up);
+                    linear_velocity.0 += gravity.up * (jump_impulse.0 - up_speed);
+                    jump_count.current += 1;
+                    jump_events.send(JumpEvent {
+                        entity: event.entity,
+                    });
                 }
-                MovementAction::Climb(direction) => {
-                    if is_climbing {
-                        linear_velocity.x = 0.;
-                        linear_velocity.y = 0.;
-                        position.y += *direction * movement_acceleration.0 * 0.25 * delta_time;
-                    }
+            }
+            MovementAction::Climb(direction) => {
+                if is_noclip {
+                    position.y += direction * movement_acceleration.0 * delta_time;
+                } else if is_climbing {
+                    linear_velocity.x = 0.;
+                    linear_velocity.y = 0.;
+                    position.y += direction * movement_acceleration.0 * 0.25 * delta_time;
                 }
             }
         }
     }
 }
 
-/// Applies [`ControllerGravity`] to character controllers.
+/// Applies [`EffectiveGravity`] to character controllers.
 fn apply_gravity(
     time: Res<Time>,
-    mut controllers: Query<(&ControllerGravity, &mut LinearVelocity, Has<Climbing>)>,
+    mut controllers: Query<
+        (&EffectiveGravity, &mut LinearVelocity, Has<Climbing>),
+        Without<Noclip>,
+    >,
 ) {
     // Precision is adjusted so that the example works with
     // both the `f32` and `f64` features. Otherwise you don't need this.
@@ -394,22 +584,146 @@ fn apply_gravity(
 
     for (gravity, mut linear_velocity, is_climbing) in &mut controllers {
         if !is_climbing {
-            linear_velocity.0 += gravity.0 * delta_time;
+            linear_velocity.0 += gravity.acceleration * delta_time;
+        }
+    }
+}
+
+/// Keeps the [`KnockbackEvent`]s of character controllers until the next fixed step.
+///
+/// The latest knockback wins, the longest hitstun.
+fn latch_knockback(
+    mut commands: Commands,
+    mut knockback_events: EventReader<KnockbackEvent>,
+    controllers: Query<Option<&PendingKnockback>, With<CharacterController>>,
+) {
+    let mut latched: HashMap<Entity, PendingKnockback> = HashMap::new();
+    for event in knockback_events.read() {
+        let Ok(pending) = controllers.get(event.target) else {
+            continue;
+        };
+        let pending = latched
+            .entry(event.target)
+            .or_insert_with(|| PendingKnockback {
+                knockback: pending.map_or(Vector::ZERO, |pending| pending.knockback),
+                hitstun: pending.map_or(0.0, |pending| pending.hitstun),
+            });
+        if event.knockback != Vector::ZERO {
+            pending.knockback = event.knockback;
+        }
+        pending.hitstun = pending.hitstun.max(event.hitstun);
+    }
+
+    for (target, pending) in latched {
+        commands.entity(target).insert(pending);
+    }
+}
+
+/// Launches character controllers hit by knockback, knocking them off ropes.
+fn apply_knockback(
+    mut commands: Commands,
+    mut controllers: Query<(Entity, &PendingKnockback, &mut LinearVelocity)>,
+) {
+    for (target, pending, mut linear_velocity) in &mut controllers {
+        let mut entity = commands.entity(target);
+        entity.remove::<PendingKnockback>();
+        if pending.knockback != Vector::ZERO {
+            linear_velocity.0 = pending.knockback;
+            entity
+                .insert(KnockbackRecovery(Timer::from_seconds(
+                    KNOCKBACK_RECOVERY_SECS,
+                    TimerMode::Once,
+                )))
+                .remove::<Climbing>()
+                .remove::<Sensor>();
+        }
+        if pending.hitstun > 0.0 {
+            entity.insert(Hitstun(Timer::from_seconds(
+                pending.hitstun,
+                TimerMode::Once,
+            )));
+        }
+    }
+}
+
+/// Removes [`KnockbackRecovery`] and [`Hitstun`] once they run out.
+fn tick_hit_reactions(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut recovering: Query<(Entity, &mut KnockbackRecovery)>,
+    mut stunned: Query<(Entity, &mut Hitstun)>,
+) {
+    for (entity, mut recovery) in &mut recovering {
+        if recovery.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<KnockbackRecovery>();
         }
     }
+    for (entity, mut hitstun) in &mut stunned {
+        if hitstun.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<Hitstun>();
+        }
+    }
+}
+
+fn store_previous_positions(mut query: Query<(&Position, &mut PhysicsInterpolation)>) {
+    for (position, mut interpolation) in &mut query {
+        interpolation.previous = Some(position.0);
+    }
 }
 
-/// Slows down movement in the X direction, Y if climbing.
+fn store_current_positions(mut query: Query<(&Position, &mut PhysicsInterpolation)>) {
+    for (position, mut interpolation) in &mut query {
+        interpolation.current = Some(position.0);
+    }
+}
+
+/// The set in which bodies are moved to where they are drawn, anything drawn along with them
+/// reads their [`GlobalTransform`] after it.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct InterpolateSet;
+
+/// Draws bodies between their last two physics positions.
+///
+/// Only the [`GlobalTransform`] is touched, so `Transform` and `Position` stay exactly what the
+/// physics step left them at.
+fn interpolate_transforms(
+    fixed_time: Res<Time<Fixed>>,
+    mut query: Query<(&PhysicsInterpolation, &mut GlobalTransform)>,
+) {
+    let overstep = fixed_time.overstep_percentage() as Scalar;
+    for (interpolation, mut global_transform) in &mut query {
+        let (Some(previous), Some(current)) = (interpolation.previous, interpolation.current)
+        else {
+            continue;
+        };
+        let mut transform = global_transform.compute_transform();
+        let position = previous.lerp(current, overstep);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        *global_transform = GlobalTransform::from(transform);
+    }
+}
+
+/// Slows down movement along the walking axis, Y if climbing.
 fn apply_movement_damping(
-    mut query: Query<(&MovementDampingFactor, &mut LinearVelocity, Has<Climbing>)>,
+    mut query: Query<
+        (
+            &MovementDampingFactor,
+            &EffectiveGravity,
+            &mut LinearVelocity,
+            Has<Climbing>,
+        ),
+        Without<KnockbackRecovery>,
+    >,
 ) {
-    for (damping_factor, mut linear_velocity, is_climbing) in &mut query {
-        // We could use `LinearDamping`, but we don't want to dampen movement along the Y axis when
-        // not climbing.
+    for (damping_factor, gravity, mut linear_velocity, is_climbing) in &mut query {
+        // We could use `LinearDamping`, but we don't want to dampen falling when not climbing.
         if is_climbing {
             linear_velocity.y *= damping_factor.0;
         } else {
-            linear_velocity.x *= damping_factor.0;
+            let forward = gravity.forward();
+            let speed = linear_velocity.dot(forward);
+            linear_velocity.0 -= forward * speed * (1.0 - damping_factor.0);
         }
     }
 }
@@ -430,6 +744,7 @@ fn kinematic_controller_collisions(
             &mut Position,
             &Rotation,
             &mut LinearVelocity,
+            &EffectiveGravity,
             Option<&MaxSlopeAngle>,
         ),
         With<CharacterController>,
@@ -452,7 +767,7 @@ fn kinematic_controller_collisions(
         // Get the body of the character controller and whether it is the first
         // or second entity in the collision.
         let is_first: bool;
-        let (rb, mut position, rotation, mut linear_velocity, max_slope_angle) =
+        let (rb, mut position, rotation, mut linear_velocity, gravity, max_slope_angle) =
             if let Ok(character) = character_controllers.get_mut(collider_parent1.get()) {
                 is_first = true;
                 character
@@ -483,11 +798,13 @@ fn kinematic_controller_collisions(
             }
 
             // If the slope isn't too steep to walk on but the character
-            // is falling, reset vertical velocity.
-            if max_slope_angle.is_some_and(|angle| normal.angle_between(Vector::Y).abs() <= angle.0)
-                && linear_velocity.y < 0.0
+            // is falling, reset the velocity along gravity.
+            let up_speed = linear_velocity.dot(gravity.up);
+            if max_slope_angle
+                .is_some_and(|angle| normal.angle_between(gravity.up).abs() <= angle.0)
+                && up_speed < 0.0
             {
-                linear_velocity.y = linear_velocity.y.max(0.0);
+                linear_velocity.0 -= gravity.up * up_speed;
             }
         }
     }
@@ -0,0 +1,306 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+
+use bevy::{prelude::*, time::Stopwatch};
+use serde::{Deserialize, Serialize};
+
+use super::combat::{ApplyDamageSet, KillEvent};
+use super::hud::HudTarget;
+use super::items::{Inventory, ItemId, ItemStack};
+use super::player::{Player, Survivor};
+use super::run_rng::RunRng;
+use crate::engine::config::{data_path, write_atomic};
+use crate::AppState;
+
+pub const PROFILE_FILE: &str = "profile.ron";
+/// Bumped whenever the format changes in a way older profiles have to be migrated from.
+pub const PROFILE_VERSION: u32 = 1;
+/// How many runs the history keeps, the oldest are dropped first.
+pub const RUN_HISTORY_LEN: usize = 20;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifetimeStats {
+    pub runs: u32,
+    pub kills: u32,
+    pub deaths: u32,
+    pub furthest_stage: u32,
+    pub longest_run_secs: f32,
+}
+
+/// How a finished run went.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub seed: u64,
+    pub survivor: Survivor,
+    pub items: Vec<ItemStack>,
+    pub stage: u32,
+    pub time_secs: f32,
+    pub kills: u32,
+    /// What killed the last player standing, if it had a name.
+    pub cause_of_death: Option<String>,
+}
+
+/// Everything kept between sessions, saved to [`PROFILE_FILE`] in the data directory.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub version: u32,
+    pub unlocked_survivors: BTreeSet<Survivor>,
+    /// Items found in any run so far.
+    pub unlocked_items: BTreeSet<ItemId>,
+    pub stats: LifetimeStats,
+    /// The most recent runs, newest last.
+    pub history: VecDeque<RunRecord>,
+    /// Set when the saved profile couldn't be understood, so it isn't overwritten.
+    #[serde(skip)]
+    read_only: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            version: PROFILE_VERSION,
+            unlocked_survivors: BTreeSet::from([Survivor::Commando]),
+            unlocked_items: BTreeSet::new(),
+            stats: LifetimeStats::default(),
+            history: VecDeque::new(),
+            read_only: false,
+        }
+    }
+}
+
+/// Just the version of a saved profile, read first to know how to read the rest.
+#[derive(Deserialize)]
+struct ProfileHeader {
+    #[serde(default)]
+    version: u32,
+}
+
+impl Profile {
+    /// Reads a saved profile, migrating it from older versions.
+    fn parse(contents: &str) -> Result<Self, String> {
+        let header: ProfileHeader = ron::from_str(contents).map_err(|err| err.to_string())?;
+        match header.version {
+            PROFILE_VERSION => ron::from_str(contents).map_err(|err| err.to_string()),
+            // Migrations from older versions go here, each upgrading to the next version.
+            version if version > PROFILE_VERSION => Err(format!(
+                "it was saved by a newer version of the game (profile version {version})"
+            )),
+            version => Err(format!("unknown profile version {version}")),
+        }
+    }
+
+    /// Loads the saved profile, or starts a new one if there isn't one yet.
+    ///
+    /// A profile that can't be read is left alone rather than overwritten with a new one.
+    pub fn load() -> Self {
+        let Some(path) = data_path(PROFILE_FILE) else {
+            return Self::default();
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+
+        match Self::parse(&contents) {
+            Ok(profile) => profile,
+            Err(err) => {
+                error!(
+                    "Can't read the profile at {}, progress won't be saved: {err}",
+                    path.display()
+                );
+                Self {
+                    read_only: true,
+                    ..default()
+                }
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if self.read_only {
+            return;
+        }
+        let Some(path) = data_path(PROFILE_FILE) else {
+            warn!("No data directory available, not saving the profile");
+            return;
+        };
+
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!("Failed to serialize the profile: {err}");
+                return;
+            }
+        };
+        match write_atomic(&path, contents.as_bytes()) {
+            Ok(()) => info!("Saved {}", path.display()),
+            Err(err) => warn!("Failed to write {}: {err}", path.display()),
+        }
+    }
+
+    /// Adds a finished run to the lifetime stats and the history, and unlocks its items.
+    pub fn record_run(&mut self, run: RunRecord, deaths: u32) {
+        self.stats.runs += 1;
+        self.stats.kills += run.kills;
+        self.stats.deaths += deaths;
+        self.stats.furthest_stage = self.stats.furthest_stage.max(run.stage);
+        self.stats.longest_run_secs = self.stats.longest_run_secs.max(run.time_secs);
+
+        self.unlocked_items
+            .extend(run.items.iter().map(|stack| stack.item));
+
+        self.history.push_back(run);
+        while self.history.len() > RUN_HISTORY_LEN {
+            self.history.pop_front();
+        }
+    }
+}
+
+/// How the current run is going, recorded into the [`Profile`] when it ends.
+#[derive(Resource, Reflect, Default)]
+#[reflect(Resource)]
+pub struct RunStats {
+    /// The run clock, only ticking while in game.
+    pub time: Stopwatch,
+    pub stage: u32,
+    /// Enemies killed by any player.
+    pub kills: u32,
+    pub deaths: u32,
+    pub cause_of_death: Option<String>,
+}
+
+pub(crate) fn reset_run_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats {
+        stage: 1,
+        ..default()
+    };
+}
+
+fn tick_run_clock(time: Res<Time>, mut stats: ResMut<RunStats>) {
+    stats.time.tick(time.delta());
+}
+
+fn count_kills(
+    mut stats: ResMut<RunStats>,
+    mut kill_events: EventReader<KillEvent>,
+    players: Query<(), With<Player>>,
+    names: Query<&Name>,
+) {
+    for event in kill_events.read() {
+        if event
+            .attacker
+            .is_some_and(|attacker| players.contains(attacker))
+        {
+            stats.kills += 1;
+        }
+        if players.contains(event.victim) {
+            stats.deaths += 1;
+            stats.cause_of_death = event
+                .attacker
+                .and_then(|attacker| names.get(attacker).ok())
+                .map(|name| name.to_string());
+        }
+    }
+}
+
+/// Records the run that just ended from the point of view of the player the HUD follows.
+fn record_run(
+    mut profile: ResMut<Profile>,
+    stats: Res<RunStats>,
+    run_rng: Res<RunRng>,
+    players: Query<(&Survivor, &Inventory, Has<HudTarget>), With<Player>>,
+) {
+    let player = players
+        .iter()
+        .find(|(_, _, is_hud_target)| *is_hud_target)
+        .or_else(|| players.iter().next());
+    let Some((survivor, inventory, _)) = player else {
+        return;
+    };
+
+    let run = RunRecord {
+        seed: run_rng.seed(),
+        survivor: *survivor,
+        items: inventory.stacks().to_vec(),
+        stage: stats.stage,
+        time_secs: stats.time.elapsed_secs(),
+        kills: stats.kills,
+        cause_of_death: stats.cause_of_death.clone(),
+    };
+    profile.record_run(run, stats.deaths);
+    profile.save();
+}
+
+/// Keeps the [`RunStats`] of the current run, part of the simulation so the run clock and kill
+/// count work even when nothing is saved, e.g. while playing a replay back.
+pub struct RunStatsPlugin;
+
+impl Plugin for RunStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RunStats>()
+            .init_resource::<RunStats>()
+            .add_systems(OnEnter(AppState::InGame), reset_run_stats)
+            .add_systems(
+                Update,
+                (tick_run_clock, count_kills.after(ApplyDamageSet))
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Loads the [`Profile`] and records every finished run into it.
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Profile::load())
+            .add_systems(OnEnter(AppState::GameOver), record_run);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(kills: u32) -> RunRecord {
+        RunRecord {
+            seed: 7,
+            survivor: Survivor::Commando,
+            items: vec![ItemStack {
+                item: ItemId::Ukulele,
+                count: 2,
+            }],
+            stage: 1,
+            time_secs: 60.0,
+            kills,
+            cause_of_death: Some("Dummy".to_string()),
+        }
+    }
+
+    #[test]
+    fn profile_round_trips_through_ron() {
+        let mut profile = Profile::default();
+        profile.record_run(run(3), 1);
+        let ron = ron::to_string(&profile).unwrap();
+        assert_eq!(Profile::parse(&ron).unwrap(), profile);
+        assert!(profile.unlocked_items.contains(&ItemId::Ukulele));
+    }
+
+    #[test]
+    fn history_keeps_the_latest_runs() {
+        let mut profile = Profile::default();
+        for kills in 0..RUN_HISTORY_LEN as u32 + 5 {
+            profile.record_run(run(kills), 1);
+        }
+        assert_eq!(profile.history.len(), RUN_HISTORY_LEN);
+        assert_eq!(profile.history[0].kills, 5);
+        assert_eq!(profile.stats.runs, RUN_HISTORY_LEN as u32 + 5);
+    }
+
+    #[test]
+    fn newer_profiles_are_not_read() {
+        let ron = format!("(version: {}, stats: (runs: 3))", PROFILE_VERSION + 1);
+        assert!(Profile::parse(&ron).is_err());
+    }
+}
@@ -0,0 +1,318 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use super::combat::{DamageEvent, Health, ProcChainMask, Team};
+use super::net::AuthoritySet;
+use super::physics_layers::Layer;
+use super::util::RunEntity;
+
+#[derive(Component)]
+pub struct Projectile {
+    pub owner: Option<Entity>,
+    pub team: Team,
+    pub damage: f32,
+    pub proc_coefficient: f32,
+    pub proc_chain: ProcChainMask,
+}
+
+/// Despawns the projectile when the timer runs out, explosive projectiles explode first.
+#[derive(Component, Deref, DerefMut)]
+pub struct ProjectileLifetime(pub Timer);
+
+/// The number of additional targets a projectile can pass through.
+#[derive(Component)]
+pub struct Pierce(pub u32);
+
+/// Steers the projectile towards a target, keeping its speed.
+#[derive(Component)]
+pub struct Homing {
+    pub target: Entity,
+    /// Maximum turn rate in radians per second.
+    pub turn_rate: f32,
+}
+
+/// Makes a projectile explode on impact (or when its lifetime ends) instead of hitting a single target.
+#[derive(Component)]
+pub struct Explosive {
+    pub radius: f32,
+}
+
+/// The targets a projectile already damaged, so a piercing projectile hits each one only once.
+#[derive(Component, Default)]
+pub struct ProjectileHits(Vec<Entity>);
+
+/// A bundle that contains the components needed for a basic projectile.
+///
+/// Add [`Pierce`], [`Homing`] or [`Explosive`] next to it for special behaviour.
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    projectile: Projectile,
+    lifetime: ProjectileLifetime,
+    hits: ProjectileHits,
+    rigid_body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+    col_layers: CollisionLayers,
+    position: Position,
+    velocity: LinearVelocity,
+    gravity_scale: GravityScale,
+    sprite: SpriteBundle,
+    run_entity: RunEntity,
+}
+
+impl ProjectileBundle {
+    pub fn new(
+        team: Team,
+        owner: Option<Entity>,
+        position: Vec2,
+        velocity: Vec2,
+        damage: f32,
+    ) -> Self {
+        Self {
+            projectile: Projectile {
+                owner,
+                team,
+                damage,
+                proc_coefficient: 1.0,
+                proc_chain: ProcChainMask::default(),
+            },
+            lifetime: ProjectileLifetime(Timer::from_seconds(3.0, TimerMode::Once)),
+            hits: ProjectileHits::default(),
+            rigid_body: RigidBody::Dynamic,
+            collider: Collider::cuboid(2.0, 2.0),
+            sensor: Sensor,
+            col_layers: CollisionLayers::new(
+                [team.projectile_layer()],
+                [team.opponent().body_layer(), Layer::Ground],
+            ),
+            position: Position(position),
+            velocity: LinearVelocity(velocity),
+            // Bullets fly straight, grenades opt into gravity.
+            gravity_scale: GravityScale(0.0),
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    color: match team {
+                        Team::Player => Color::YELLOW,
+                        Team::Enemy => Color::ORANGE_RED,
+                    },
+                    custom_size: Some(Vec2::new(2.0, 2.0)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(-1.0)),
+                ..default()
+            },
+            run_entity: RunEntity,
+        }
+    }
+
+    pub fn with_lifetime(mut self, seconds: f32) -> Self {
+        self.lifetime = ProjectileLifetime(Timer::from_seconds(seconds, TimerMode::Once));
+        self
+    }
+
+    pub fn with_gravity_scale(mut self, gravity_scale: f32) -> Self {
+        self.gravity_scale = GravityScale(gravity_scale);
+        self
+    }
+
+    pub fn with_size(mut self, size: Vec2) -> Self {
+        self.collider = Collider::cuboid(size.x, size.y);
+        self.sprite.sprite.custom_size = Some(size);
+        self
+    }
+
+    pub fn with_proc(mut self, proc_coefficient: f32, proc_chain: ProcChainMask) -> Self {
+        self.projectile.proc_coefficient = proc_coefficient;
+        self.projectile.proc_chain = proc_chain;
+        self
+    }
+}
+
+/// An event sent to damage everything of the opposing team in a radius.
+#[derive(Event, Clone, Debug)]
+pub struct ExplosionEvent {
+    pub attacker: Option<Entity>,
+    pub team: Team,
+    pub position: Vec2,
+    pub radius: f32,
+    pub damage: f32,
+    pub proc_coefficient: f32,
+    pub proc_chain: ProcChainMask,
+}
+
+impl ExplosionEvent {
+    fn from_projectile(projectile: &Projectile, position: Vec2, radius: f32) -> Self {
+        Self {
+            attacker: projectile.owner,
+            team: projectile.team,
+            position,
+            radius,
+            damage: projectile.damage,
+            proc_coefficient: projectile.proc_coefficient,
+            proc_chain: projectile.proc_chain,
+        }
+    }
+}
+
+fn tick_projectile_lifetime(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut explosions: EventWriter<ExplosionEvent>,
+    mut query: Query<(
+        Entity,
+        &Projectile,
+        &Position,
+        &mut ProjectileLifetime,
+        Option<&Explosive>,
+    )>,
+) {
+    for (entity, projectile, position, mut lifetime, explosive) in &mut query {
+        lifetime.tick(time.delta());
+        if lifetime.just_finished() {
+            if let Some(explosive) = explosive {
+                explosions.send(ExplosionEvent::from_projectile(
+                    projectile,
+                    position.0,
+                    explosive.radius,
+                ));
+            }
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn steer_homing_projectiles(
+    time: Res<Time>,
+    mut projectiles: Query<(&Homing, &Position, &mut LinearVelocity)>,
+    targets: Query<&Position>,
+) {
+    for (homing, position, mut velocity) in &mut projectiles {
+        // Keep flying straight once the target is gone.
+        let Ok(target) = targets.get(homing.target) else {
+            continue;
+        };
+
+        let speed = velocity.length();
+        let desired = (target.0 - position.0).normalize_or_zero();
+        if speed == 0.0 || desired == Vec2::ZERO {
+            continue;
+        }
+
+        let current = velocity.0 / speed;
+        let max_turn = homing.turn_rate * time.delta_seconds();
+        let angle = current.angle_between(desired).clamp(-max_turn, max_turn);
+        velocity.0 = Vec2::from_angle(angle).rotate(current) * speed;
+    }
+}
+
+fn projectile_collisions(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionStarted>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut explosions: EventWriter<ExplosionEvent>,
+    mut projectiles: Query<(
+        &Projectile,
+        &Position,
+        &mut ProjectileHits,
+        Option<&mut Pierce>,
+        Option<&Explosive>,
+    )>,
+    targets: Query<(), With<Health>>,
+) {
+    // Projectiles that ended this frame can still be part of later collision events.
+    let mut ended = Vec::new();
+    for CollisionStarted(entity1, entity2) in collisions.read() {
+        let (entity, other) = if projectiles.contains(*entity1) {
+            (*entity1, *entity2)
+        } else if projectiles.contains(*entity2) {
+            (*entity2, *entity1)
+        } else {
+            continue;
+        };
+        let Ok((projectile, position, mut hits, pierce, explosive)) = projectiles.get_mut(entity)
+        else {
+            continue;
+        };
+        if ended.contains(&entity) || hits.0.contains(&other) {
+            continue;
+        }
+
+        let is_target = targets.contains(other);
+        if is_target {
+            hits.0.push(other);
+        }
+
+        if let Some(explosive) = explosive {
+            explosions.send(ExplosionEvent::from_projectile(
+                projectile,
+                position.0,
+                explosive.radius,
+            ));
+        } else if is_target {
+            damage_events.send(
+                DamageEvent::new(projectile.owner, other, projectile.damage)
+                    .with_proc_coefficient(projectile.proc_coefficient)
+                    .with_proc_chain(projectile.proc_chain),
+            );
+        }
+
+        // Piercing projectiles keep going through targets, but never through walls.
+        let keeps_going = match pierce {
+            Some(mut pierce) if is_target && explosive.is_none() && pierce.0 > 0 => {
+                pierce.0 -= 1;
+                true
+            }
+            _ => false,
+        };
+        if !keeps_going {
+            ended.push(entity);
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn explode(
+    spatial_query: SpatialQuery,
+    mut explosions: EventReader<ExplosionEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    targets: Query<(), With<Health>>,
+) {
+    for explosion in explosions.read() {
+        let intersections = spatial_query.shape_intersections(
+            &Collider::ball(explosion.radius), // Shape
+            explosion.position,                // Shape position
+            0.0,                               // Shape rotation
+            SpatialQueryFilter::new().with_masks([explosion.team.opponent().body_layer()]), // Query filter
+        );
+
+        for target in intersections.into_iter().filter(|e| targets.contains(*e)) {
+            damage_events.send(
+                DamageEvent::new(explosion.attacker, target, explosion.damage)
+                    .with_proc_coefficient(explosion.proc_coefficient)
+                    .with_proc_chain(explosion.proc_chain),
+            );
+        }
+    }
+}
+
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub struct ProjectileSet;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExplosionEvent>().add_systems(
+            Update,
+            (
+                steer_homing_projectiles,
+                tick_projectile_lifetime,
+                projectile_collisions,
+                explode,
+            )
+                .chain()
+                .in_set(ProjectileSet)
+                .in_set(AuthoritySet),
+        );
+    }
+}
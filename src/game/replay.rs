@@ -0,0 +1,345 @@
+//! Recording runs and playing them back exactly.
+//!
+//! A replay is the run seed plus the [`InputIntent`] of every player for every fixed step,
+//! which is all the simulation needs to play the run out the same way again. Record a run and
+//! watch it with:
+//!
+//! ```text
+//! cargo run -- --record bug.replay
+//! cargo run -- --replay bug.replay
+//! ```
+//!
+//! While recording or playing back, every frame is exactly one fixed step long like under
+//! [`HeadlessPlugin`](crate::HeadlessPlugin), so the frame rate can't change the outcome. The
+//! game only runs at normal speed at 60 FPS then.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::{app::AppExit, prelude::*, time::TimeUpdateStrategy};
+
+use super::input::{update_action_state, ActionState, ActionStateSet};
+use super::net::protocol::{ByteReader, ByteWriter, InputIntent};
+use super::player::{PlayerCount, PlayerIndex, MAX_PLAYERS};
+use super::run_rng::RunRng;
+use crate::engine::config::write_atomic;
+use crate::{AppState, FIXED_TIMESTEP_HZ};
+
+/// The first bytes of every replay file.
+const MAGIC: &[u8; 4] = b"RRPL";
+/// Bumped whenever the format, or the simulation in a way that breaks older replays, changes.
+pub const REPLAY_VERSION: u8 = 1;
+/// The longest replay that is recorded or loaded, four hours of fixed steps. Guards against
+/// corrupt step counts unpacking into more memory than the machine has.
+pub const MAX_REPLAY_TICKS: usize = (4.0 * 60.0 * 60.0 * FIXED_TIMESTEP_HZ) as usize;
+
+/// The inputs of a whole run.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub player_count: u32,
+    /// The intents of every player, by index, for each fixed step of the run.
+    pub ticks: Vec<Vec<InputIntent>>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, player_count: u32) -> Self {
+        Self {
+            seed,
+            player_count,
+            ticks: Vec::new(),
+        }
+    }
+
+    /// Packs the replay, runs of steps with the same intents are stored once with a count.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut runs: Vec<(u32, &[InputIntent])> = Vec::new();
+        for intents in &self.ticks {
+            match runs.last_mut() {
+                Some((count, last)) if *last == intents.as_slice() => *count += 1,
+                _ => runs.push((1, intents)),
+            }
+        }
+
+        let mut writer = ByteWriter(MAGIC.to_vec());
+        writer.u8(REPLAY_VERSION);
+        writer.u64(self.seed);
+        writer.u32(self.player_count);
+        writer.u32(runs.len() as u32);
+        for (count, intents) in runs {
+            writer.u32(count);
+            for intent in intents {
+                intent.write(&mut writer);
+            }
+        }
+        writer.0
+    }
+
+    /// Unpacks a replay, `None` if it is malformed, from another version or longer than
+    /// [`MAX_REPLAY_TICKS`].
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = ByteReader(bytes);
+        if reader.bytes::<4>()? != *MAGIC || reader.u8()? != REPLAY_VERSION {
+            return None;
+        }
+        let seed = reader.u64()?;
+        let player_count = reader.u32()?;
+        if player_count > MAX_PLAYERS {
+            return None;
+        }
+
+        let mut ticks = Vec::new();
+        for _ in 0..reader.u32()? {
+            let count = reader.u32()? as usize;
+            if count > MAX_REPLAY_TICKS - ticks.len() {
+                return None;
+            }
+            let intents = (0..player_count)
+                .map(|_| InputIntent::read(&mut reader))
+                .collect::<Option<Vec<_>>>()?;
+            ticks.extend(std::iter::repeat_n(intents, count));
+        }
+        Some(Self {
+            seed,
+            player_count,
+            ticks,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|err| err.to_string())?;
+        Self::decode(&bytes).ok_or_else(|| "not a replay of this version of the game".to_string())
+    }
+
+    pub fn save(&self, path: &Path) {
+        match write_atomic(path, &self.encode()) {
+            Ok(()) => info!(
+                "Saved a replay of {} steps to {}",
+                self.ticks.len(),
+                path.display()
+            ),
+            Err(err) => warn!("Failed to write {}: {err}", path.display()),
+        }
+    }
+}
+
+/// Records every run into a [`Replay`], saved to `path` when the run ends.
+#[derive(Resource)]
+pub struct ReplayRecorder {
+    path: PathBuf,
+    replay: Replay,
+}
+
+/// Plays a [`Replay`] back, one step per frame.
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    replay: Replay,
+    tick: usize,
+}
+
+/// Whether to record or play back a replay, parsed from the command line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReplayMode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Play(PathBuf),
+}
+
+impl ReplayMode {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => {
+                    if let Some(path) = args.next() {
+                        return ReplayMode::Record(path.into());
+                    }
+                }
+                "--replay" => {
+                    if let Some(path) = args.next() {
+                        return ReplayMode::Play(path.into());
+                    }
+                }
+                _ => {}
+            }
+        }
+        ReplayMode::Off
+    }
+}
+
+/// Starts a new run that plays `replay` back from its first step.
+pub fn start_playback(app: &mut App, replay: Replay) {
+    app.insert_resource(RunRng::new(replay.seed))
+        .insert_resource(PlayerCount(replay.player_count))
+        .insert_resource(ReplayPlayer { replay, tick: 0 })
+        .insert_resource(NextState(Some(AppState::InGame)));
+}
+
+fn start_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    run_rng: Res<RunRng>,
+    player_count: Res<PlayerCount>,
+) {
+    recorder.replay = Replay::new(run_rng.seed(), player_count.0);
+}
+
+fn record_inputs(
+    mut recorder: ResMut<ReplayRecorder>,
+    players: Query<(&PlayerIndex, &ActionState)>,
+) {
+    // A longer replay wouldn't load again, keep the first hours.
+    if recorder.replay.ticks.len() >= MAX_REPLAY_TICKS {
+        return;
+    }
+    let mut intents = vec![InputIntent::default(); recorder.replay.player_count as usize];
+    for (index, action_state) in &players {
+        if let Some(intent) = intents.get_mut(index.0 as usize) {
+            *intent = InputIntent::from_action_state(action_state);
+        }
+    }
+    recorder.replay.ticks.push(intents);
+}
+
+fn save_recording(recorder: Res<ReplayRecorder>) {
+    recorder.replay.save(&recorder.path);
+}
+
+/// Overrides the players' input with the next step of the replay, until it runs out.
+fn play_inputs(
+    mut commands: Commands,
+    mut player: ResMut<ReplayPlayer>,
+    mut players: Query<(&PlayerIndex, &mut ActionState)>,
+) {
+    let Some(intents) = player.replay.ticks.get(player.tick) else {
+        info!("The replay ended after {} steps", player.tick);
+        commands.remove_resource::<ReplayPlayer>();
+        return;
+    };
+    for (index, mut action_state) in &mut players {
+        let intent = intents.get(index.0 as usize).copied().unwrap_or_default();
+        intent.apply(&mut action_state);
+    }
+    player.tick += 1;
+}
+
+/// Records or plays back replays as picked with [`ReplayMode`].
+///
+/// With [`ReplayMode::Off`] nothing is recorded, but a replay can still be started with
+/// [`start_playback`], e.g. from a headless test.
+#[derive(Default)]
+pub struct ReplayPlugin {
+    pub mode: ReplayMode,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (
+                play_inputs
+                    .in_set(ActionStateSet)
+                    .after(update_action_state)
+                    .run_if(resource_exists::<ReplayPlayer>()),
+                record_inputs
+                    .after(ActionStateSet)
+                    .run_if(resource_exists::<ReplayRecorder>()),
+            )
+                .run_if(in_state(AppState::InGame)),
+        );
+
+        match &self.mode {
+            ReplayMode::Off => return,
+            ReplayMode::Record(path) => {
+                info!("Recording runs to {}", path.display());
+                app.insert_resource(ReplayRecorder {
+                    path: path.clone(),
+                    replay: Replay::default(),
+                })
+                .add_systems(OnEnter(AppState::InGame), start_recording)
+                .add_systems(OnExit(AppState::InGame), save_recording)
+                // Quitting mid-run never leaves the state, save what was recorded so far.
+                .add_systems(
+                    Last,
+                    save_recording
+                        .run_if(on_event::<AppExit>().and_then(in_state(AppState::InGame))),
+                );
+            }
+            ReplayMode::Play(path) => match Replay::load(path) {
+                Ok(replay) => start_playback(app, replay),
+                Err(err) => {
+                    error!("Can't play back {}: {err}", path.display());
+                    return;
+                }
+            },
+        }
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / FIXED_TIMESTEP_HZ,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent(movement: f32, buttons: u32) -> InputIntent {
+        InputIntent {
+            movement,
+            climb: 0.0,
+            buttons,
+        }
+    }
+
+    #[test]
+    fn replay_round_trips() {
+        let mut replay = Replay::new(1234, 2);
+        replay
+            .ticks
+            .extend(vec![vec![InputIntent::default(); 2]; 30]);
+        replay.ticks.push(vec![intent(1.0, 2), intent(-0.5, 0)]);
+        replay
+            .ticks
+            .extend(vec![vec![intent(1.0, 0), intent(0.0, 0)]; 30]);
+
+        let bytes = replay.encode();
+        // Three runs of a count and two intents, repeated steps aren't stored again.
+        let header_len = Replay::new(1234, 2).encode().len();
+        assert_eq!(bytes.len(), header_len + 3 * (4 + 2 * 12));
+        assert_eq!(Replay::decode(&bytes), Some(replay));
+    }
+
+    #[test]
+    fn malformed_replays_are_rejected() {
+        let bytes = Replay::new(1, 1).encode();
+        assert_eq!(Replay::decode(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Replay::decode(b"not a replay"), None);
+
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()] = REPLAY_VERSION + 1;
+        assert_eq!(Replay::decode(&newer), None);
+
+        // A step count far past the limit, without the intents to back it up.
+        let mut endless = ByteWriter(Replay::new(1, 1).encode());
+        let runs = endless.0.len() - 4;
+        endless.0[runs..].copy_from_slice(&1u32.to_le_bytes());
+        endless.u32(u32::MAX);
+        InputIntent::default().write(&mut endless);
+        assert_eq!(Replay::decode(&endless.0), None);
+    }
+
+    #[test]
+    fn parses_command_line() {
+        let args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(ReplayMode::from_args(args(&["game"])), ReplayMode::Off);
+        assert_eq!(
+            ReplayMode::from_args(args(&["game", "--record", "run.replay"])),
+            ReplayMode::Record("run.replay".into())
+        );
+        assert_eq!(
+            ReplayMode::from_args(args(&["game", "--host", "7777", "--replay", "a"])),
+            ReplayMode::Play("a".into())
+        );
+    }
+}
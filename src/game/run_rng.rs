@@ -0,0 +1,100 @@
+use bevy::prelude::*;
+
+use crate::engine::rng::Rng;
+
+/// An independent random stream of a run.
+///
+/// Every system rolls from its own stream, so e.g. an extra proc roll can't change which chests
+/// drop what. New streams must be appended at the end to keep existing seeds stable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Loot,
+    Director,
+    Elite,
+    Proc,
+}
+
+impl RngStream {
+    pub const COUNT: usize = 4;
+}
+
+/// The random number generator of the current run, created from the run seed.
+///
+/// Saved with the run, so a continued run rolls exactly what it would have rolled.
+#[derive(Resource, Reflect)]
+#[reflect(Resource)]
+pub struct RunRng {
+    seed: u64,
+    streams: [Rng; RngStream::COUNT],
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        let mut seeder = Rng::new(seed);
+        Self {
+            seed,
+            streams: std::array::from_fn(|_| Rng::new(seeder.next_u64())),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut Rng {
+        &mut self.streams[stream as usize]
+    }
+}
+
+impl Default for RunRng {
+    fn default() -> Self {
+        Self::new(random_seed())
+    }
+}
+
+/// A seed for runs that weren't given one.
+pub fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_streams() {
+        let mut a = RunRng::new(1337);
+        let mut b = RunRng::new(1337);
+
+        for _ in 0..100 {
+            assert_eq!(
+                a.stream(RngStream::Loot).next_u64(),
+                b.stream(RngStream::Loot).next_u64()
+            );
+        }
+    }
+
+    #[test]
+    fn streams_are_independent() {
+        let mut a = RunRng::new(1337);
+        let mut b = RunRng::new(1337);
+
+        // Extra rolls on one stream must not shift any of the others.
+        for _ in 0..50 {
+            a.stream(RngStream::Proc).next_u64();
+        }
+        for stream in [RngStream::Loot, RngStream::Director, RngStream::Elite] {
+            assert_eq!(a.stream(stream).next_u64(), b.stream(stream).next_u64());
+        }
+    }
+
+    #[test]
+    fn streams_differ() {
+        let mut rng = RunRng::new(0);
+        let loot = rng.stream(RngStream::Loot).next_u64();
+        let director = rng.stream(RngStream::Director).next_u64();
+        assert_ne!(loot, director);
+    }
+}
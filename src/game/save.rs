@@ -0,0 +1,213 @@
+use std::fs;
+
+use bevy::{prelude::*, scene::serde::SceneDeserializer, utils::HashMap};
+use serde::de::DeserializeSeed;
+
+use super::combat::{Barrier, Dead, Health};
+use super::items::Inventory;
+use super::player::{spawn_player, Gold, Player, PlayerIndex, PlayerLevel, PlayerXp, Survivor};
+use super::player_controller::{JumpCount, JumpImpulse, MovementAcceleration};
+use super::profile::{reset_run_stats, RunStats};
+use super::run_rng::RunRng;
+use super::stats::{JumpsStat, SpeedStat};
+use super::util::RunEntity;
+use crate::engine::config::{data_path, write_atomic};
+use crate::AppState;
+
+pub const SAVE_FILE: &str = "run.scn.ron";
+
+/// A run saved with "save and quit", loaded in the main menu so it can be continued.
+#[derive(Resource)]
+pub struct SavedRun(DynamicScene);
+
+impl SavedRun {
+    pub fn player_count(&self) -> u32 {
+        self.0.entities.len() as u32
+    }
+}
+
+/// The state of the run that can't be rebuilt: the players' progress, the run clock and the
+/// RNG. Sprites, colliders and the like are spawned as usual when the run is continued.
+///
+/// The controller components are what the player actually moves with, saved so speed and
+/// jumps changed during the run carry over.
+fn run_scene(world: &mut World) -> DynamicScene {
+    let players: Vec<Entity> = world
+        .query_filtered::<Entity, With<Player>>()
+        .iter(world)
+        .collect();
+
+    DynamicSceneBuilder::from_world(world)
+        .allow::<PlayerIndex>()
+        .allow::<Survivor>()
+        .allow::<PlayerXp>()
+        .allow::<PlayerLevel>()
+        .allow::<Gold>()
+        .allow::<Inventory>()
+        .allow::<Health>()
+        .allow::<Barrier>()
+        .allow::<Dead>()
+        .allow::<SpeedStat>()
+        .allow::<JumpsStat>()
+        .allow::<MovementAcceleration>()
+        .allow::<JumpImpulse>()
+        .allow::<JumpCount>()
+        .allow_resource::<RunRng>()
+        .allow_resource::<RunStats>()
+        .extract_entities(players.into_iter())
+        .extract_resources()
+        .build()
+}
+
+fn parse_run(contents: &str, registry: &AppTypeRegistry) -> Result<DynamicScene, String> {
+    let mut deserializer = ron::Deserializer::from_str(contents).map_err(|err| err.to_string())?;
+    SceneDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)
+    .map_err(|err| err.to_string())
+}
+
+pub(crate) fn load_saved_run(mut commands: Commands, registry: Res<AppTypeRegistry>) {
+    let Some(path) = data_path(SAVE_FILE) else {
+        return;
+    };
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return;
+    };
+
+    match parse_run(&contents, &registry) {
+        Ok(scene) => commands.insert_resource(SavedRun(scene)),
+        Err(err) => warn!("Ignoring unreadable saved run {}: {err}", path.display()),
+    }
+}
+
+/// Saves the run and leaves it for the main menu, chosen from the
+/// [pause menu](super::pause_menu).
+pub(crate) fn save_and_quit(world: &mut World) {
+    let scene = run_scene(world);
+    let registry = world.resource::<AppTypeRegistry>().clone();
+
+    match (scene.serialize_ron(&registry), data_path(SAVE_FILE)) {
+        (Ok(contents), Some(path)) => match write_atomic(&path, contents.as_bytes()) {
+            Ok(()) => info!("Saved the run to {}", path.display()),
+            Err(err) => warn!("Failed to write {}: {err}", path.display()),
+        },
+        (Err(err), _) => warn!("Failed to serialize the run: {err}"),
+        (_, None) => warn!("No data directory available, not saving the run"),
+    }
+
+    let run_entities: Vec<Entity> = world
+        .query_filtered::<Entity, With<RunEntity>>()
+        .iter(world)
+        .collect();
+    for entity in run_entities {
+        // Children with their own `RunEntity` are already gone with their parent.
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn_recursive();
+        }
+    }
+    world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Menu);
+}
+
+/// Applies the [`SavedRun`] to the freshly spawned players, if the player chose to continue it.
+///
+/// A save can only be continued once, starting any run discards it.
+fn restore_run(world: &mut World) {
+    if let Some(path) = data_path(SAVE_FILE) {
+        if path.exists() {
+            if let Err(err) = fs::remove_file(&path) {
+                warn!("Failed to remove {}: {err}", path.display());
+            }
+        }
+    }
+    let Some(SavedRun(mut scene)) = world.remove_resource::<SavedRun>() else {
+        return;
+    };
+
+    let players: HashMap<u32, Entity> = world
+        .query_filtered::<(Entity, &PlayerIndex), With<Player>>()
+        .iter(world)
+        .map(|(entity, index)| (index.0, entity))
+        .collect();
+
+    // Saved players are matched to the spawned ones by their index, anyone left over (which
+    // only happens with a hand-edited save) is dropped rather than spawned half-built.
+    let mut entity_map = HashMap::default();
+    scene.entities.retain(|saved| {
+        let player = saved
+            .components
+            .iter()
+            .filter(|component| component.represents::<PlayerIndex>())
+            .find_map(|component| PlayerIndex::from_reflect(&**component))
+            .and_then(|index| players.get(&index.0));
+        if let Some(&player) = player {
+            entity_map.insert(saved.entity, player);
+        }
+        player.is_some()
+    });
+
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    match scene.write_to_world_with(world, &mut entity_map, &registry) {
+        Ok(()) => info!("Continuing the saved run"),
+        Err(err) => warn!("Failed to restore the saved run: {err}"),
+    }
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Menu), load_saved_run)
+            .add_systems(
+                OnEnter(AppState::InGame),
+                restore_run.after(spawn_player).after(reset_run_stats),
+            );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_round_trips_through_a_scene() {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<PlayerIndex>();
+            registry.register::<Gold>();
+            registry.register::<Health>();
+            registry.register::<JumpCount>();
+            registry.register::<JumpImpulse>();
+            registry.register::<RunStats>();
+            // Registered by Bevy's own plugins in the game.
+            registry.register::<bevy::time::Stopwatch>();
+            registry.register::<std::time::Duration>();
+            registry.register::<Option<String>>();
+        }
+        world.insert_resource(registry.clone());
+        world.insert_resource(RunStats {
+            kills: 12,
+            ..default()
+        });
+        world.spawn((
+            Player,
+            PlayerIndex(1),
+            Gold(40),
+            Health::new(80.0),
+            JumpCount::new(2),
+            JumpImpulse(150.0),
+        ));
+
+        let scene = run_scene(&mut world);
+        let contents = scene.serialize_ron(&registry).unwrap();
+        let saved = SavedRun(parse_run(&contents, &registry).unwrap());
+        assert_eq!(saved.player_count(), 1);
+        assert_eq!(saved.0.resources.len(), 1);
+        assert_eq!(saved.0.entities[0].components.len(), 5);
+    }
+}
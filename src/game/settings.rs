@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::audio::AudioSettings;
+use super::input::InputMap;
+use crate::engine::config::{load_config, save_config};
+use crate::engine::display::DisplaySettings;
+
+pub const SETTINGS_FILE: &str = "settings.ron";
+
+/// Options that change how the game plays out on screen, not the simulation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameplaySettings {
+    /// Scales how far the camera shakes, 0 turns it off.
+    pub screen_shake: f32,
+    pub damage_numbers: bool,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            screen_shake: 1.0,
+            damage_numbers: true,
+        }
+    }
+}
+
+/// Every user preference. Loaded from [`SETTINGS_FILE`] in the config directory on startup,
+/// and saved when leaving the settings screen.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub display: DisplaySettings,
+    pub audio: AudioSettings,
+    pub gameplay: GameplaySettings,
+    pub input: InputMap,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        load_config(SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        save_config(SETTINGS_FILE, self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_sections_fall_back_to_defaults() {
+        let settings: Settings = ron::from_str("(gameplay: (damage_numbers: false))").unwrap();
+        assert!(!settings.gameplay.damage_numbers);
+        assert_eq!(settings.gameplay.screen_shake, 1.0);
+        assert_eq!(settings.audio.master, AudioSettings::default().master);
+    }
+}
@@ -0,0 +1,293 @@
+use bevy::prelude::*;
+
+use super::input::{Action, RawInputs};
+use super::menu::{screen_root, text_style, MenuInput};
+use super::settings::Settings;
+use super::util::despawn_with;
+use crate::engine::display::{
+    cycle_preset, max_scale_label, window_mode_label, MAX_SCALES, WINDOW_MODES,
+};
+use crate::{AppState, GameFont};
+
+const DEADZONE_STEP: f32 = 0.05;
+const VOLUME_STEP: f32 = 0.1;
+const SCREEN_SHAKE_STEP: f32 = 0.25;
+const MAX_DEADZONE: f32 = 0.9;
+
+#[derive(Component)]
+struct OnSettingsScreen;
+
+/// The text of one [`SettingsRow`], by index.
+#[derive(Component)]
+struct SettingsRowText(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SettingsRow {
+    Binding { action: Action, positive: bool },
+    StickDeadzone,
+    TriggerDeadzone,
+    WindowMode,
+    FrameLimit,
+    Resolution,
+    MaxScale,
+    MasterVolume,
+    MusicVolume,
+    SfxVolume,
+    ScreenShake,
+    DamageNumbers,
+}
+
+fn settings_rows() -> Vec<SettingsRow> {
+    let mut rows = Vec::new();
+    for action in Action::ALL {
+        rows.push(SettingsRow::Binding {
+            action,
+            positive: true,
+        });
+        if action.is_axis() {
+            rows.push(SettingsRow::Binding {
+                action,
+                positive: false,
+            });
+        }
+    }
+    rows.push(SettingsRow::StickDeadzone);
+    rows.push(SettingsRow::TriggerDeadzone);
+    rows.extend([
+        SettingsRow::WindowMode,
+        SettingsRow::FrameLimit,
+        SettingsRow::Resolution,
+        SettingsRow::MaxScale,
+        SettingsRow::MasterVolume,
+        SettingsRow::MusicVolume,
+        SettingsRow::SfxVolume,
+        SettingsRow::ScreenShake,
+        SettingsRow::DamageNumbers,
+    ]);
+    rows
+}
+
+/// The selected row, and whether it is waiting for a new binding.
+#[derive(Resource, Default)]
+struct SettingsCursor {
+    row: usize,
+    rebinding: bool,
+}
+
+fn row_label(row: SettingsRow, settings: &Settings) -> String {
+    let Settings {
+        display,
+        audio,
+        gameplay,
+        input: input_map,
+    } = settings;
+    match row {
+        SettingsRow::Binding { action, positive } => {
+            let name = match (action.is_axis(), positive) {
+                (false, _) => action.name().to_string(),
+                (true, true) => format!("{} +", action.name()),
+                (true, false) => format!("{} -", action.name()),
+            };
+            let bindings = input_map
+                .bindings(action)
+                .map(|bindings| {
+                    let list = if positive {
+                        &bindings.positive
+                    } else {
+                        &bindings.negative
+                    };
+                    list.iter()
+                        .map(|binding| binding.label())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            format!("{name}: {bindings}")
+        }
+        SettingsRow::StickDeadzone => format!("Stick deadzone: {:.2}", input_map.stick_deadzone),
+        SettingsRow::TriggerDeadzone => {
+            format!("Trigger deadzone: {:.2}", input_map.trigger_deadzone)
+        }
+        SettingsRow::WindowMode => format!("Window: {}", window_mode_label(display.window_mode)),
+        SettingsRow::FrameLimit => format!("Frame limit: {}", display.frame_limit.label()),
+        SettingsRow::Resolution => format!("Resolution: {}", display.resolution.label()),
+        SettingsRow::MaxScale => format!("Max scale: {}", max_scale_label(display.max_scale)),
+        SettingsRow::MasterVolume => format!("Master volume: {:.0}%", audio.master * 100.0),
+        SettingsRow::MusicVolume => format!("Music volume: {:.0}%", audio.music * 100.0),
+        SettingsRow::SfxVolume => format!("Sound volume: {:.0}%", audio.sfx * 100.0),
+        SettingsRow::ScreenShake => {
+            format!("Screen shake: {:.0}%", gameplay.screen_shake * 100.0)
+        }
+        SettingsRow::DamageNumbers => format!(
+            "Damage numbers: {}",
+            if gameplay.damage_numbers { "On" } else { "Off" }
+        ),
+    }
+}
+
+fn spawn_settings(mut commands: Commands, game_font: Res<GameFont>) {
+    commands.insert_resource(SettingsCursor::default());
+    commands
+        .spawn((
+            Name::new("SettingsMenu"),
+            OnSettingsScreen,
+            screen_root(crate::CLEAR_COLOR),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Settings",
+                text_style(&game_font, 48.0, Color::WHITE),
+            ));
+            for index in 0..settings_rows().len() {
+                parent.spawn((
+                    SettingsRowText(index),
+                    TextBundle::from_section("", text_style(&game_font, 16.0, Color::WHITE)),
+                ));
+            }
+            parent.spawn(TextBundle::from_section(
+                "Up/Down to select, Enter (A) to rebind, Left/Right to adjust, Escape (B) to go back",
+                text_style(&game_font, 16.0, Color::GRAY),
+            ));
+        });
+}
+
+/// Moves a value from 0 to 1 by `step` in `direction`, rounded to whole steps so it can get
+/// back to exactly 0.
+fn step_fraction(value: f32, step: f32, direction: i32) -> f32 {
+    ((value / step).round() + direction as f32).clamp(0.0, 1.0 / step) * step
+}
+
+fn settings_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    menu_input: MenuInput,
+    inputs: RawInputs,
+    mut cursor: ResMut<SettingsCursor>,
+    mut settings: ResMut<Settings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let rows = settings_rows();
+
+    if cursor.rebinding {
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            cursor.rebinding = false;
+        } else if let Some(binding) = inputs.just_pressed_binding() {
+            if let SettingsRow::Binding { action, positive } = rows[cursor.row] {
+                settings.input.rebind(action, positive, binding);
+            }
+            cursor.rebinding = false;
+        }
+        return;
+    }
+
+    if menu_input.back() {
+        settings.save();
+        next_state.set(AppState::Menu);
+        return;
+    }
+
+    if menu_input.down() {
+        cursor.row = (cursor.row + 1) % rows.len();
+    }
+    if menu_input.up() {
+        cursor.row = (cursor.row + rows.len() - 1) % rows.len();
+    }
+
+    let direction = if menu_input.right() {
+        1
+    } else if menu_input.left() {
+        -1
+    } else {
+        0
+    };
+    let step = direction as f32 * DEADZONE_STEP;
+
+    let row = rows[cursor.row];
+    if let SettingsRow::Binding { .. } = row {
+        if menu_input.confirm() {
+            cursor.rebinding = true;
+        }
+        return;
+    }
+    // Settings are applied when they change, so don't touch them unless something does.
+    if direction == 0 {
+        return;
+    }
+
+    let Settings {
+        display,
+        audio,
+        gameplay,
+        input: input_map,
+    } = &mut *settings;
+    match row {
+        SettingsRow::Binding { .. } => {}
+        SettingsRow::StickDeadzone => {
+            input_map.stick_deadzone = (input_map.stick_deadzone + step).clamp(0.0, MAX_DEADZONE);
+        }
+        SettingsRow::TriggerDeadzone => {
+            input_map.trigger_deadzone =
+                (input_map.trigger_deadzone + step).clamp(0.0, MAX_DEADZONE);
+        }
+        SettingsRow::WindowMode => {
+            display.window_mode = cycle_preset(&WINDOW_MODES, display.window_mode, direction);
+        }
+        SettingsRow::FrameLimit => display.frame_limit = display.frame_limit.cycle(direction),
+        SettingsRow::Resolution => display.resolution = display.resolution.cycle(direction),
+        SettingsRow::MaxScale => {
+            display.max_scale = cycle_preset(&MAX_SCALES, display.max_scale, direction);
+        }
+        SettingsRow::MasterVolume => {
+            audio.master = step_fraction(audio.master, VOLUME_STEP, direction);
+        }
+        SettingsRow::MusicVolume => {
+            audio.music = step_fraction(audio.music, VOLUME_STEP, direction);
+        }
+        SettingsRow::SfxVolume => audio.sfx = step_fraction(audio.sfx, VOLUME_STEP, direction),
+        SettingsRow::ScreenShake => {
+            gameplay.screen_shake =
+                step_fraction(gameplay.screen_shake, SCREEN_SHAKE_STEP, direction);
+        }
+        SettingsRow::DamageNumbers => gameplay.damage_numbers = !gameplay.damage_numbers,
+    }
+}
+
+fn update_settings_text(
+    cursor: Res<SettingsCursor>,
+    settings: Res<Settings>,
+    mut texts: Query<(&SettingsRowText, &mut Text)>,
+) {
+    if !cursor.is_changed() && !settings.is_changed() {
+        return;
+    }
+
+    let rows = settings_rows();
+    for (row_text, mut text) in &mut texts {
+        let selected = row_text.0 == cursor.row;
+        let section = &mut text.sections[0];
+        section.value = if selected && cursor.rebinding {
+            "Press a key or button...".to_string()
+        } else {
+            row_label(rows[row_text.0], &settings)
+        };
+        section.style.color = if selected {
+            Color::YELLOW
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::Settings), spawn_settings)
+            .add_systems(
+                Update,
+                (settings_input, update_settings_text)
+                    .chain()
+                    .run_if(in_state(AppState::Settings)),
+            )
+            .add_systems(OnExit(AppState::Settings), despawn_with::<OnSettingsScreen>);
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+/// The four skill slots every survivor has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum SkillSlot {
+    Primary,
+    Secondary,
+    Utility,
+    Special,
+}
+
+impl SkillSlot {
+    pub const ALL: [SkillSlot; 4] = [
+        SkillSlot::Primary,
+        SkillSlot::Secondary,
+        SkillSlot::Utility,
+        SkillSlot::Special,
+    ];
+}
+
+/// A skill with a cooldown that restores one charge at a time.
+#[derive(Clone, Debug, Reflect)]
+pub struct Skill {
+    pub name: String,
+    pub charges: u32,
+    pub max_charges: u32,
+    cooldown: Timer,
+}
+
+impl Skill {
+    pub fn new(name: &str, cooldown: f32, max_charges: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            charges: max_charges,
+            max_charges,
+            cooldown: Timer::from_seconds(cooldown, TimerMode::Once),
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.charges > 0
+    }
+
+    /// Spends a charge, returns false if there was none left.
+    pub fn try_use(&mut self) -> bool {
+        if !self.is_ready() {
+            return false;
+        }
+        self.charges -= 1;
+        true
+    }
+
+    /// How much of the cooldown for the next charge is left, `0.0` when fully charged.
+    pub fn cooldown_fraction(&self) -> f32 {
+        if self.charges >= self.max_charges {
+            0.0
+        } else {
+            self.cooldown.percent_left()
+        }
+    }
+
+    pub fn cooldown_remaining(&self) -> f32 {
+        if self.charges >= self.max_charges {
+            0.0
+        } else {
+            self.cooldown.remaining_secs()
+        }
+    }
+
+    fn tick(&mut self, delta: std::time::Duration) {
+        if self.charges >= self.max_charges {
+            return;
+        }
+        if self.cooldown.tick(delta).just_finished() {
+            self.charges += 1;
+            self.cooldown.reset();
+        }
+    }
+}
+
+/// The skill loadout of a survivor, indexed by [`SkillSlot`].
+#[derive(Component, Clone, Debug, Reflect)]
+pub struct Skills([Skill; 4]);
+
+impl Skills {
+    pub fn new(primary: Skill, secondary: Skill, utility: Skill, special: Skill) -> Self {
+        Self([primary, secondary, utility, special])
+    }
+
+    pub fn get(&self, slot: SkillSlot) -> &Skill {
+        &self.0[slot as usize]
+    }
+
+    pub fn get_mut(&mut self, slot: SkillSlot) -> &mut Skill {
+        &mut self.0[slot as usize]
+    }
+}
+
+fn tick_skill_cooldowns(time: Res<Time>, mut query: Query<&mut Skills>) {
+    for mut skills in &mut query {
+        // Don't trigger change detection for loadouts that are fully charged.
+        if skills
+            .0
+            .iter()
+            .all(|skill| skill.charges >= skill.max_charges)
+        {
+            continue;
+        }
+        for skill in &mut skills.0 {
+            skill.tick(time.delta());
+        }
+    }
+}
+
+pub struct SkillsPlugin;
+
+impl Plugin for SkillsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Skills>()
+            .add_systems(Update, tick_skill_cooldowns);
+    }
+}
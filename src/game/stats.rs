@@ -69,10 +69,12 @@ pub struct StatPair {
     current: f32,
 }
 
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct SpeedStat(pub f32);
 
-#[derive(Component, Reflect)]
+#[derive(Component, Reflect, Default)]
+#[reflect(Component)]
 pub struct JumpsStat {
     pub max_jumps: u32,
     pub jumps_left: u32,
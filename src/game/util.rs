@@ -1 +1,13 @@
-pub const MAX_STAT_MODIFIERS: usize = 5; //Currently arbitrary.
+use bevy::prelude::*;
+
+pub const MAX_STAT_MODIFIERS: usize = 5; //Currently arbitrary.
+
+/// A marker component for entities that belong to the current run, despawned when the run ends.
+#[derive(Component)]
+pub struct RunEntity;
+
+pub fn despawn_with<T: Component>(mut commands: Commands, query: Query<Entity, With<T>>) {
+    for entity in &query {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,324 @@
+#![feature(trivial_bounds)]
+pub mod assets;
+#[cfg(feature = "dev")]
+pub mod dev;
+pub mod engine;
+pub mod game;
+
+use std::time::Duration;
+
+use crate::assets::*;
+use crate::game::enemy::dummy::spawn_temp_dummy;
+use crate::game::physics_layers::Layer;
+use crate::game::player::PlayerPlugin;
+
+use bevy::{
+    diagnostic::FrameTimeDiagnosticsPlugin, input::InputPlugin, prelude::*, text::TextSettings,
+    time::TimeUpdateStrategy,
+};
+
+use bevy_xpbd_2d::prelude::*;
+
+use engine::display::DisplayPlugin;
+use engine::fps_text::{record_frame_times, spawn_fps_text, text_update_system, FrameTimes};
+use engine::frame_pacing::FramePacingPlugin;
+use engine::pixel_camera::PixelCameraPlugin;
+use engine::rng::Rng;
+use game::audio::SoundPlugin;
+use game::camera::{CameraPlugin, StageBounds};
+use game::clock::{clock_text_update_system, spawn_clock_text};
+use game::combat::CombatPlugin;
+use game::enemy::EnemyPlugin;
+use game::floating_text::FloatingTextPlugin;
+use game::gravity_zone::{GravityZone, GravityZoneBundle};
+use game::hitbox::HitboxPlugin;
+use game::hud::HudPlugin;
+use game::input::InputMapPlugin;
+use game::items::ItemsPlugin;
+use game::menu::MenuPlugin;
+use game::net::{NetMode, NetPlugin};
+use game::pause_menu::PauseMenuPlugin;
+use game::profile::{ProfilePlugin, RunStatsPlugin};
+use game::projectile::ProjectilePlugin;
+use game::replay::{ReplayMode, ReplayPlugin};
+use game::run_rng::{random_seed, RngStream, RunRng};
+use game::save::SavePlugin;
+use game::settings::Settings;
+use game::settings_menu::SettingsMenuPlugin;
+use game::skills::SkillsPlugin;
+use game::stats::StatsPlugin;
+use game::util::RunEntity;
+
+pub const CLEAR_COLOR: Color = Color::rgb(0.270588, 0.266666, 0.309803);
+pub const TEXT_SCALE: f32 = 4.0;
+
+/// The default game resolution, see [`engine::pixel_camera::GameResolution`] for the others.
+pub const GAME_WIDTH: f32 = 320.0;
+pub const GAME_HEIGHT: f32 = 240.0;
+
+/// The default gravity, of physics bodies and character controllers alike.
+pub const GRAVITY: Vec2 = Vec2::new(0.0, -1000.0);
+
+/// The rate of `FixedUpdate`, which runs physics and the character controllers.
+pub const FIXED_TIMESTEP_HZ: f64 = 60.0;
+
+/// The length of one frame in a [`HeadlessPlugin`] app, exactly one fixed step.
+pub const HEADLESS_TIMESTEP: f64 = 1.0 / FIXED_TIMESTEP_HZ;
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
+pub enum AppState {
+    #[default]
+    Menu,
+    Settings,
+    InGame,
+    GameOver,
+}
+
+/// The simulation: physics, stats, combat, items, input and players.
+///
+/// Physics and the character controllers run in `FixedUpdate` at [`FIXED_TIMESTEP_HZ`].
+/// The user's [`Settings`] are loaded here too, since input and the camera depend on them.
+///
+/// Doesn't need a window or a GPU, so it can run under [`HeadlessPlugin`] as well as
+/// `DefaultPlugins`. Menus, the HUD and other presentation are added by [`build_app`].
+pub struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<AppState>()
+            .insert_resource(Time::<Fixed>::from_hz(FIXED_TIMESTEP_HZ))
+            .add_plugins(PhysicsPlugins::new(FixedUpdate))
+            .insert_resource(PhysicsTimestep::FixedOnce(
+                (1.0 / FIXED_TIMESTEP_HZ) as Scalar,
+            ))
+            .insert_resource(SubstepCount(12))
+            .insert_resource(Gravity(GRAVITY))
+            .register_type::<Rng>()
+            .register_type::<[Rng; RngStream::COUNT]>()
+            .register_type::<RunRng>()
+            .insert_resource(RunRng::new(random_seed()))
+            .insert_resource(Settings::load())
+            .add_plugins((
+                StatsPlugin,
+                CombatPlugin,
+                ItemsPlugin,
+                ProjectilePlugin,
+                HitboxPlugin,
+                SkillsPlugin,
+                InputMapPlugin,
+                PlayerPlugin,
+                CameraPlugin,
+                EnemyPlugin,
+                RunStatsPlugin,
+            ))
+            .add_systems(
+                OnEnter(AppState::InGame),
+                (
+                    spawn_temp_floor,
+                    spawn_temp_dummy,
+                    spawn_rope,
+                    spawn_gravity_zones,
+                ),
+            );
+    }
+}
+
+/// Adds the whole game on top of `DefaultPlugins`: the simulation, its presentation, netcode,
+/// saving and replays, plus the debug tooling when built with the `dev` feature.
+///
+/// This is everything the binary does besides opening a window, so tests can check what a build
+/// ships with.
+pub fn build_app(app: &mut App, net_mode: NetMode, replay_mode: ReplayMode) {
+    app.add_plugins((
+        FrameTimeDiagnosticsPlugin,
+        DisplayPlugin,
+        FramePacingPlugin,
+        PixelCameraPlugin,
+    ))
+    .insert_resource(TextSettings {
+        allow_dynamic_font_size: false,
+        ..default()
+    })
+    .add_plugins(GamePlugin)
+    .add_plugins((
+        HudPlugin,
+        FloatingTextPlugin,
+        MenuPlugin,
+        SettingsMenuPlugin,
+        SoundPlugin,
+    ))
+    .insert_resource(Msaa::Off)
+    .insert_resource(ClearColor(CLEAR_COLOR))
+    .init_resource::<FrameTimes>()
+    .add_systems(PreStartup, load_game_font)
+    .add_systems(Startup, (spawn_fps_text, spawn_clock_text))
+    .add_systems(
+        Update,
+        (
+            (record_frame_times, text_update_system).chain(),
+            clock_text_update_system,
+        ),
+    )
+    .add_plugins(NetPlugin { mode: net_mode });
+
+    #[cfg(feature = "dev")]
+    app.add_plugins(dev::DevPlugin);
+
+    // Replays always start from the seed, so a saved run can't be continued while recording,
+    // and a played back run isn't one of the player's. The pause menu is where a run is saved.
+    // A client's run is the host's, only the host keeps its stats and can save or pause it.
+    let client = matches!(net_mode, NetMode::Client { .. });
+    match replay_mode {
+        _ if client => {}
+        ReplayMode::Off => {
+            app.add_plugins((ProfilePlugin, SavePlugin, PauseMenuPlugin));
+        }
+        ReplayMode::Record(_) => {
+            app.add_plugins(ProfilePlugin);
+        }
+        ReplayMode::Play(_) => {}
+    }
+    app.add_plugins(ReplayPlugin { mode: replay_mode });
+}
+
+fn load_game_font(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(GameFont(asset_server.load("fonts/a4ep.ttf")));
+}
+
+/// The parts of Bevy [`GamePlugin`] relies on, without a window, a renderer or audio.
+///
+/// Every update advances time by exactly [`HEADLESS_TIMESTEP`], so runs are reproducible no
+/// matter how fast the machine is. Sprites are still loaded, but never drawn.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default_nearest(),
+            TransformPlugin,
+            HierarchyPlugin,
+            InputPlugin,
+        ))
+        .init_asset::<TextureAtlas>()
+        .init_asset::<Font>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            HEADLESS_TIMESTEP,
+        )))
+        .insert_resource(GameFont(Handle::default()));
+    }
+}
+
+#[derive(Component)]
+pub struct Climbable;
+
+fn spawn_rope(mut commands: Commands) {
+    // Rectangle
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.25, 0.25, 0.75),
+                custom_size: Some(Vec2::new(2.0, 40.0)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(Vec3::new(-40., -190., -2.)),
+            ..Default::default()
+        },
+        Climbable,
+        RunEntity,
+        Name::new("Rope"),
+        Sensor,
+        Collider::cuboid(0.1, 40.0),
+        CollisionLayers::new([Layer::Climbable], []),
+    ));
+}
+
+fn spawn_gravity_zones(mut commands: Commands) {
+    let zones = [
+        (
+            "Low_Gravity",
+            GravityZone::Scale(0.3),
+            Vec2::new(-150.0, -112.0),
+            Vec2::new(60.0, 146.0),
+            Color::rgba(0.5, 0.75, 1.0, 0.15),
+        ),
+        (
+            "Gravity_Lift",
+            GravityZone::Set(Vec2::new(0.0, 600.0)),
+            Vec2::new(190.0, -132.0),
+            Vec2::new(30.0, 106.0),
+            Color::rgba(1.0, 0.85, 0.4, 0.2),
+        ),
+    ];
+
+    for (name, zone, center, size, color) in zones {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(size),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(center.extend(-4.0)),
+                ..Default::default()
+            },
+            GravityZoneBundle::new(zone, size),
+            RunEntity,
+            Name::new(name),
+        ));
+    }
+}
+
+#[derive(Component)]
+pub struct Ground;
+
+fn spawn_temp_floor(mut commands: Commands, assets: Res<AssetServer>) {
+    commands.insert_resource(StageBounds(Rect::new(-256.0, -239.0, 256.0, 120.0)));
+
+    commands.spawn((
+        Name::new("Temp_Floor"),
+        Ground,
+        RunEntity,
+        SpriteBundle {
+            texture: assets.load("sprites/temp_floor.png"),
+            transform: Transform::from_xyz(0.0, -232.0, -3.0),
+            ..Default::default()
+        },
+        RigidBody::Static,
+        Collider::cuboid(252.0, 14.0),
+        Friction::new(0.0),
+        CollisionLayers::new(
+            [Layer::Ground],
+            [
+                Layer::Player,
+                Layer::Enemy,
+                Layer::PlayerProjectile,
+                Layer::EnemyProjectile,
+            ],
+        ),
+    ));
+
+    commands.spawn((
+        Name::new("Temp_Floor"),
+        Ground,
+        RunEntity,
+        SpriteBundle {
+            texture: assets.load("sprites/temp_floor.png"),
+            transform: Transform::from_xyz(0., -192.01, -3.0),
+            ..Default::default()
+        },
+        RigidBody::Static,
+        Collider::cuboid(252.0, 14.0),
+        Friction::new(0.0),
+        CollisionLayers::new(
+            [Layer::Ground],
+            [
+                Layer::Player,
+                Layer::Enemy,
+                Layer::PlayerProjectile,
+                Layer::EnemyProjectile,
+            ],
+        ),
+    ));
+}
@@ -0,0 +1,176 @@
+//! Hit reactions of players: knockback, hitstun and invulnerability frames.
+mod common;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use common::*;
+use risk_of_rust::game::combat::{DamageEvent, Dead, Health, IFramesOnHit};
+use risk_of_rust::game::items::{Inventory, ItemId};
+use risk_of_rust::game::net::protocol::InputIntent;
+use risk_of_rust::game::player_controller::{Hitstun, KnockbackRecovery, KNOCKBACK_RECOVERY_SECS};
+use risk_of_rust::game::projectile::Homing;
+use risk_of_rust::FIXED_TIMESTEP_HZ;
+
+/// The number of fixed steps in `secs` seconds, rounded up.
+fn ticks(secs: f32) -> u32 {
+    (secs as f64 * FIXED_TIMESTEP_HZ).ceil() as u32
+}
+
+fn hit(app: &mut App, event: DamageEvent) {
+    app.world.send_event(event);
+}
+
+fn health(app: &App, entity: Entity) -> f32 {
+    app.world.get::<Health>(entity).unwrap().current
+}
+
+fn walk_right() -> InputIntent {
+    InputIntent {
+        movement: 1.0,
+        ..default()
+    }
+}
+
+#[test]
+fn knockback_overrides_damping_until_recovered() {
+    let mut app = headless_app(1);
+    let player = player(&mut app, 0);
+
+    hit(
+        &mut app,
+        DamageEvent::new(None, player, 1.0).with_knockback(Vec2::new(200.0, 0.0)),
+    );
+    // Damage is applied in `Update`, the knockback in the next fixed step.
+    run(&mut app, 2, InputIntent::default());
+    assert!(app.world.get::<KnockbackRecovery>(player).is_some());
+
+    run(
+        &mut app,
+        ticks(KNOCKBACK_RECOVERY_SECS) - 2,
+        InputIntent::default(),
+    );
+    assert_eq!(get::<LinearVelocity>(&app, player).x, 200.0);
+
+    run(&mut app, 30, InputIntent::default());
+    assert!(app.world.get::<KnockbackRecovery>(player).is_none());
+    assert!(get::<LinearVelocity>(&app, player).x < 10.0);
+}
+
+#[test]
+fn hitstun_blocks_input() {
+    let mut app = headless_app(1);
+    let player = player(&mut app, 0);
+
+    hit(
+        &mut app,
+        DamageEvent::new(None, player, 1.0).with_hitstun(0.5),
+    );
+    run(&mut app, 2, InputIntent::default());
+    assert!(app.world.get::<Hitstun>(player).is_some());
+
+    let start = get::<Position>(&app, player).x;
+    run(&mut app, ticks(0.5) - 4, walk_right());
+    assert_eq!(get::<Position>(&app, player).x, start);
+
+    run(&mut app, 10, walk_right());
+    assert!(app.world.get::<Hitstun>(player).is_none());
+    assert!(get::<Position>(&app, player).x > start);
+}
+
+#[test]
+fn iframes_block_follow_up_hits() {
+    let mut app = headless_app(1);
+    let player = player(&mut app, 0);
+    let start = health(&app, player);
+
+    // A burst of hits in one frame only lands the first.
+    for _ in 0..3 {
+        hit(&mut app, DamageEvent::new(None, player, 10.0));
+    }
+    step(&mut app, InputIntent::default());
+    assert_eq!(health(&app, player), start - 10.0);
+
+    hit(&mut app, DamageEvent::new(None, player, 10.0));
+    step(&mut app, InputIntent::default());
+    assert_eq!(health(&app, player), start - 10.0);
+
+    // Players get 0.75 seconds of i-frames.
+    run(&mut app, ticks(0.75) + 1, InputIntent::default());
+    hit(&mut app, DamageEvent::new(None, player, 10.0));
+    step(&mut app, InputIntent::default());
+    assert_eq!(health(&app, player), start - 20.0);
+}
+
+#[test]
+fn hit_reactions_land_at_any_frame_rate() {
+    for fps in [144.0, 240.0] {
+        let mut app = headless_app(1);
+        set_frame_rate(&mut app, fps);
+        let player = player(&mut app, 0);
+
+        // Land hits at different points between two fixed steps, waiting out the i-frames.
+        for phase in 0..4 {
+            run(&mut app, phase, InputIntent::default());
+            hit(
+                &mut app,
+                DamageEvent::new(None, player, 1.0)
+                    .with_knockback(Vec2::new(200.0, 0.0))
+                    .with_hitstun(0.1),
+            );
+            let mut reacted = false;
+            for _ in 0..(fps / 10.0) as u32 {
+                step(&mut app, InputIntent::default());
+                reacted |= app.world.get::<Hitstun>(player).is_some();
+            }
+            assert!(reacted, "hit {phase} dropped at {fps} FPS");
+            run(&mut app, fps as u32, InputIntent::default());
+        }
+    }
+}
+
+#[test]
+fn blocked_hits_roll_no_procs() {
+    let mut app = headless_app(1);
+    let attacker = player(&mut app, 0);
+    // Enough AtG Missiles to fire on every hit.
+    app.world
+        .get_mut::<Inventory>(attacker)
+        .unwrap()
+        .add(ItemId::AtgMissile, 10);
+    let target = app
+        .world
+        .spawn((
+            TransformBundle::default(),
+            RigidBody::Kinematic,
+            Health::new(100.0),
+            IFramesOnHit(1.0),
+        ))
+        .id();
+    let corpse = app
+        .world
+        .spawn((
+            TransformBundle::default(),
+            RigidBody::Kinematic,
+            Health::new(100.0),
+            Dead,
+        ))
+        .id();
+    let missiles = |app: &mut App| {
+        app.world
+            .query_filtered::<(), With<Homing>>()
+            .iter(&app.world)
+            .count()
+    };
+
+    // Only the first of a burst lands, the rest hit the fresh i-frames.
+    for _ in 0..3 {
+        hit(&mut app, DamageEvent::new(Some(attacker), target, 10.0));
+    }
+    step(&mut app, InputIntent::default());
+    assert_eq!(missiles(&mut app), 1);
+
+    hit(&mut app, DamageEvent::new(Some(attacker), target, 10.0));
+    hit(&mut app, DamageEvent::new(Some(attacker), corpse, 10.0));
+    step(&mut app, InputIntent::default());
+    assert_eq!(missiles(&mut app), 1);
+}
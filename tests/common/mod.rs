@@ -0,0 +1,138 @@
+//! A headless game for integration tests, driven one fixed timestep at a time.
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use risk_of_rust::game::input::Action;
+use risk_of_rust::game::input::{ActionState, RemoteInput};
+use risk_of_rust::game::net::protocol::InputIntent;
+use risk_of_rust::game::player::{PlayerCount, PlayerIndex};
+use risk_of_rust::game::replay::{start_playback, Replay, ReplayMode, ReplayPlugin};
+use risk_of_rust::game::settings::Settings;
+use risk_of_rust::game::util::RunEntity;
+use risk_of_rust::{AppState, GamePlugin, HeadlessPlugin};
+
+/// Starts a run with `player_count` players, all driven by [`step`] instead of local devices.
+pub fn headless_app(player_count: u32) -> App {
+    start_run(player_count, ReplayMode::Off)
+}
+
+/// Like [`headless_app`], but the run is recorded and saved to `path` when it ends.
+pub fn recording_app(player_count: u32, path: &Path) -> App {
+    start_run(player_count, ReplayMode::Record(path.to_path_buf()))
+}
+
+fn start_run(player_count: u32, replay_mode: ReplayMode) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        HeadlessPlugin,
+        GamePlugin,
+        ReplayPlugin { mode: replay_mode },
+    ))
+    // Don't pick up the settings saved on the machine running the tests.
+    .insert_resource(Settings::default())
+    .insert_resource(PlayerCount(player_count));
+    app.finish();
+    app.cleanup();
+
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::InGame);
+    // The first update only starts the clock, physics sets up the new bodies in the first
+    // fixed step of the second.
+    app.update();
+    app.update();
+
+    let players: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<PlayerIndex>>()
+        .iter(&app.world)
+        .collect();
+    for entity in players {
+        app.world.entity_mut(entity).insert(RemoteInput);
+    }
+    app
+}
+
+/// A run playing `replay` back, one step per update from the second update on.
+pub fn replay_app(replay: Replay) -> App {
+    let mut app = App::new();
+    app.add_plugins((HeadlessPlugin, GamePlugin, ReplayPlugin::default()))
+        .insert_resource(Settings::default());
+    start_playback(&mut app, replay);
+    app.finish();
+    app.cleanup();
+    app
+}
+
+/// A run with the level and the players cleared away, for tests that build their own scene.
+pub fn empty_app() -> App {
+    let mut app = headless_app(1);
+    let run_entities: Vec<Entity> = app
+        .world
+        .query_filtered::<Entity, With<RunEntity>>()
+        .iter(&app.world)
+        .collect();
+    for entity in run_entities {
+        app.world.entity_mut(entity).despawn_recursive();
+    }
+    app
+}
+
+/// Makes every following update last `1 / fps` seconds.
+pub fn set_frame_rate(app: &mut App, fps: f64) {
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        1.0 / fps,
+    )));
+}
+
+/// The player with the given index.
+pub fn player(app: &mut App, index: u32) -> Entity {
+    app.world
+        .query::<(Entity, &PlayerIndex)>()
+        .iter(&app.world)
+        .find(|(_, player_index)| player_index.0 == index)
+        .map(|(entity, _)| entity)
+        .expect("no player with that index")
+}
+
+/// An intent pressing just `action`.
+pub fn press(action: Action) -> InputIntent {
+    let bit = Action::ALL
+        .iter()
+        .position(|a| *a == action)
+        .expect("every action is in Action::ALL");
+    InputIntent {
+        buttons: 1 << bit,
+        ..default()
+    }
+}
+
+/// Advances one timestep with every controllable entity holding `intent`.
+pub fn step(app: &mut App, intent: InputIntent) {
+    for mut action_state in app
+        .world
+        .query::<&mut ActionState>()
+        .iter_mut(&mut app.world)
+    {
+        intent.apply(&mut action_state);
+    }
+    app.update();
+}
+
+/// Advances `ticks` timesteps with every controllable entity holding `intent`.
+pub fn run(app: &mut App, ticks: u32, intent: InputIntent) {
+    for _ in 0..ticks {
+        step(app, intent);
+    }
+}
+
+/// A component of `entity`, cloned out of the world.
+pub fn get<T: Component + Clone>(app: &App, entity: Entity) -> T {
+    app.world
+        .get::<T>(entity)
+        .cloned()
+        .expect("entity is missing the component")
+}
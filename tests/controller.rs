@@ -0,0 +1,363 @@
+//! Scenario tests for the kinematic character controller, run headless at a fixed timestep.
+mod common;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::{math::*, prelude::*};
+use common::*;
+use risk_of_rust::game::gravity_zone::{GravityZone, GravityZoneBundle};
+use risk_of_rust::game::input::{Action, ActionState};
+use risk_of_rust::game::net::protocol::InputIntent;
+use risk_of_rust::game::physics_layers::Layer;
+use risk_of_rust::game::player_controller::{
+    CanClimb, CharacterControllerBundle, Climbing, ControllerGravity, Grounded, JumpCount,
+};
+use risk_of_rust::Climbable;
+
+const JUMP_IMPULSE: Scalar = 220.0;
+const HALF_HEIGHT: Scalar = 5.5;
+
+/// A controller tuned like the player, standing still at `position`.
+fn spawn_controller(app: &mut App, position: Vec2, jumps: u32) -> Entity {
+    app.world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+            CharacterControllerBundle::new(
+                Collider::cuboid(6.0, HALF_HEIGHT * 2.0),
+                Vector::NEG_Y * 1000.0,
+            )
+            .with_movement(
+                220.0,
+                0.85,
+                JUMP_IMPULSE,
+                jumps,
+                (30.0 as Scalar).to_radians(),
+            ),
+            ActionState::default(),
+        ))
+        .id()
+}
+
+/// A static block of ground, `size` across and turned `angle` degrees counterclockwise.
+fn spawn_ground(app: &mut App, center: Vec2, size: Vec2, angle: f32) {
+    app.world.spawn((
+        TransformBundle::from_transform(
+            Transform::from_translation(center.extend(0.0))
+                .with_rotation(Quat::from_rotation_z(angle.to_radians())),
+        ),
+        RigidBody::Static,
+        Collider::cuboid(size.x, size.y),
+        Friction::new(0.0),
+        CollisionLayers::new([Layer::Ground], [Layer::Player]),
+    ));
+}
+
+/// A flat floor whose top is at y = 0.
+fn spawn_floor(app: &mut App) {
+    spawn_ground(app, Vec2::new(0.0, -5.0), Vec2::new(400.0, 10.0), 0.0);
+}
+
+/// A gravity zone `size` across around `center`.
+fn spawn_zone(app: &mut App, zone: GravityZone, center: Vec2, size: Vec2) {
+    app.world.spawn((
+        TransformBundle::from_transform(Transform::from_translation(center.extend(0.0))),
+        GravityZoneBundle::new(zone, size),
+    ));
+}
+
+fn position(app: &App, entity: Entity) -> Vec2 {
+    get::<Position>(app, entity).0
+}
+
+fn velocity(app: &App, entity: Entity) -> Vec2 {
+    get::<LinearVelocity>(app, entity).0
+}
+
+fn jump_count(app: &App, entity: Entity) -> u32 {
+    app.world.get::<JumpCount>(entity).unwrap().current
+}
+
+fn has<T: Component>(app: &App, entity: Entity) -> bool {
+    app.world.get::<T>(entity).is_some()
+}
+
+/// Taps jump for one tick, then lets go for `ticks` more.
+fn jump(app: &mut App, ticks: u32) {
+    step(app, press(Action::Jump));
+    run(app, ticks, InputIntent::default());
+}
+
+#[test]
+fn lands_on_floor() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, 40.0), 1);
+
+    run(&mut app, 120, InputIntent::default());
+
+    assert!(has::<Grounded>(&app, controller));
+    assert!((position(&app, controller).y - HALF_HEIGHT).abs() < 1.0);
+    assert!(velocity(&app, controller).y.abs() < 20.0);
+    assert_eq!(jump_count(&app, controller), 0);
+}
+
+#[test]
+fn landing_resets_jump_count() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+    run(&mut app, 30, InputIntent::default());
+
+    jump(&mut app, 5);
+    assert_eq!(jump_count(&app, controller), 1);
+    assert!(!has::<Grounded>(&app, controller));
+
+    run(&mut app, 120, InputIntent::default());
+    assert!(has::<Grounded>(&app, controller));
+    assert_eq!(jump_count(&app, controller), 0);
+}
+
+#[test]
+fn single_jump_controller_cannot_jump_in_the_air() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+    run(&mut app, 30, InputIntent::default());
+
+    jump(&mut app, 10);
+    step(&mut app, press(Action::Jump));
+    assert!(velocity(&app, controller).y < JUMP_IMPULSE - 100.0);
+}
+
+#[test]
+fn double_jump_only_with_two_jumps() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 2);
+    run(&mut app, 30, InputIntent::default());
+
+    jump(&mut app, 10);
+    step(&mut app, press(Action::Jump));
+    assert!((velocity(&app, controller).y - JUMP_IMPULSE).abs() < 1.0);
+    assert_eq!(jump_count(&app, controller), 2);
+
+    // The third jump is one too many.
+    run(&mut app, 10, InputIntent::default());
+    step(&mut app, press(Action::Jump));
+    assert!(velocity(&app, controller).y < JUMP_IMPULSE - 100.0);
+}
+
+#[test]
+fn cannot_stand_on_steep_slope() {
+    let mut app = empty_app();
+    spawn_ground(&mut app, Vec2::ZERO, Vec2::new(400.0, 10.0), 60.0);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, 30.0), 1);
+
+    let mut was_grounded = false;
+    for _ in 0..120 {
+        step(&mut app, InputIntent::default());
+        was_grounded |= has::<Grounded>(&app, controller);
+    }
+
+    assert!(!was_grounded);
+    // Sliding down a slope rising to the right moves the controller left.
+    assert!(position(&app, controller).x < -5.0);
+}
+
+#[test]
+fn stands_on_gentle_slope() {
+    let mut app = empty_app();
+    spawn_ground(&mut app, Vec2::ZERO, Vec2::new(400.0, 10.0), 20.0);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, 30.0), 1);
+
+    run(&mut app, 120, InputIntent::default());
+
+    assert!(has::<Grounded>(&app, controller));
+}
+
+#[test]
+fn stops_climbing_at_rope_end() {
+    const ROPE_TOP: f32 = 40.0;
+
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    app.world.spawn((
+        TransformBundle::from_transform(Transform::from_xyz(0.0, ROPE_TOP / 2.0, 0.0)),
+        Climbable,
+        Sensor,
+        Collider::cuboid(2.0, ROPE_TOP),
+        CollisionLayers::new([Layer::Climbable], []),
+    ));
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+    run(&mut app, 30, InputIntent::default());
+
+    let climb_up = InputIntent {
+        climb: 1.0,
+        ..default()
+    };
+    let mut was_climbing = false;
+    let mut highest = Scalar::MIN;
+    for _ in 0..300 {
+        step(&mut app, climb_up);
+        let climbing = has::<Climbing>(&app, controller);
+        was_climbing |= climbing;
+        // Climbing turns the controller into a sensor so it can pass through platforms.
+        assert_eq!(climbing, has::<Sensor>(&app, controller));
+        highest = highest.max(position(&app, controller).y);
+    }
+
+    assert!(was_climbing);
+    // The controller may poke out by one climbing step before it lets go.
+    assert!(
+        highest <= ROPE_TOP + HALF_HEIGHT + 1.5,
+        "climbed to {highest}"
+    );
+}
+
+#[test]
+fn only_the_controller_on_a_rope_can_climb() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    app.world.spawn((
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 20.0, 0.0)),
+        Climbable,
+        Sensor,
+        Collider::cuboid(2.0, 40.0),
+        CollisionLayers::new([Layer::Climbable], []),
+    ));
+    let on_rope = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+    let away = spawn_controller(&mut app, Vec2::new(100.0, HALF_HEIGHT + 1.0), 1);
+
+    run(&mut app, 10, InputIntent::default());
+
+    assert!(has::<CanClimb>(&app, on_rope));
+    assert!(!has::<CanClimb>(&app, away));
+}
+
+#[test]
+fn movement_is_frame_rate_independent() {
+    let distance_at = |fps: f64| {
+        let mut app = empty_app();
+        set_frame_rate(&mut app, fps);
+        let controller = spawn_controller(&mut app, Vec2::ZERO, 1);
+        let walk = InputIntent {
+            movement: 1.0,
+            ..default()
+        };
+        run(&mut app, (fps / 2.0) as u32, walk);
+        run(&mut app, (fps * 1.5) as u32, InputIntent::default());
+        position(&app, controller).x
+    };
+
+    let expected = distance_at(60.0);
+    assert!(expected > 1.0);
+    for fps in [30.0, 240.0] {
+        let distance = distance_at(fps);
+        // Allow for the walk ending one fixed step apart.
+        assert!(
+            (distance - expected).abs() < 0.5,
+            "{distance} at {fps} FPS, {expected} at 60"
+        );
+    }
+}
+
+#[test]
+fn no_tunnelling_through_thin_floor() {
+    let mut app = empty_app();
+    spawn_ground(&mut app, Vec2::new(0.0, -1.0), Vec2::new(400.0, 2.0), 0.0);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, 100.0), 1);
+    app.world
+        .entity_mut(controller)
+        .insert(LinearVelocity(Vec2::NEG_Y * 1200.0));
+
+    run(&mut app, 60, InputIntent::default());
+
+    assert!(position(&app, controller).y > 0.0);
+    assert!(has::<Grounded>(&app, controller));
+}
+
+#[test]
+fn stands_on_ceiling_with_upside_down_gravity() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, -40.0), 1);
+    app.world
+        .entity_mut(controller)
+        .insert(ControllerGravity(Vector::Y * 1000.0));
+
+    run(&mut app, 120, InputIntent::default());
+    assert!(has::<Grounded>(&app, controller));
+    assert!((position(&app, controller).y + 10.0 + HALF_HEIGHT).abs() < 1.0);
+
+    // Jumping pushes away from the ceiling.
+    jump(&mut app, 5);
+    assert!(!has::<Grounded>(&app, controller));
+    assert!(velocity(&app, controller).y < 0.0);
+}
+
+#[test]
+fn lift_zone_carries_controller_up() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+    run(&mut app, 30, InputIntent::default());
+    assert!(has::<Grounded>(&app, controller));
+
+    spawn_zone(
+        &mut app,
+        GravityZone::Set(Vector::Y * 600.0),
+        Vec2::new(0.0, 50.0),
+        Vec2::new(40.0, 100.0),
+    );
+    run(&mut app, 30, InputIntent::default());
+
+    assert!(!has::<Grounded>(&app, controller));
+    assert!(position(&app, controller).y > 20.0);
+    assert!(velocity(&app, controller).y > 0.0);
+}
+
+#[test]
+fn jumps_higher_in_low_gravity() {
+    let peak = |zone: Option<GravityZone>| {
+        let mut app = empty_app();
+        spawn_floor(&mut app);
+        if let Some(zone) = zone {
+            spawn_zone(&mut app, zone, Vec2::ZERO, Vec2::new(400.0, 400.0));
+        }
+        let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+        run(&mut app, 30, InputIntent::default());
+
+        step(&mut app, press(Action::Jump));
+        let mut peak = position(&app, controller).y;
+        for _ in 0..120 {
+            step(&mut app, InputIntent::default());
+            peak = peak.max(position(&app, controller).y);
+        }
+        peak - HALF_HEIGHT
+    };
+
+    let normal = peak(None);
+    let low = peak(Some(GravityZone::Scale(0.3)));
+    assert!(normal > 10.0);
+    assert!(
+        low > normal * 2.5,
+        "{low} in low gravity, {normal} normally"
+    );
+}
+
+#[test]
+fn wind_pushes_without_tilting_the_ground() {
+    let mut app = empty_app();
+    spawn_floor(&mut app);
+    let controller = spawn_controller(&mut app, Vec2::new(0.0, HALF_HEIGHT + 1.0), 1);
+    spawn_zone(
+        &mut app,
+        GravityZone::Add(Vector::X * 300.0),
+        Vec2::ZERO,
+        Vec2::new(400.0, 400.0),
+    );
+
+    run(&mut app, 60, InputIntent::default());
+
+    assert!(has::<Grounded>(&app, controller));
+    assert!(position(&app, controller).x > 1.0);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_xpbd_2d::prelude::*;
+use common::*;
+use risk_of_rust::game::combat::{Dead, Health};
+use risk_of_rust::game::enemy::dummy::spawn_dummy;
+use risk_of_rust::game::enemy::PARTY_HEALTH_SCALING;
+use risk_of_rust::game::net::protocol::InputIntent;
+
+#[test]
+fn players_spawn_and_fall() {
+    let mut app = headless_app(2);
+    let first = player(&mut app, 0);
+    let second = player(&mut app, 1);
+    let start = get::<Position>(&app, first).y;
+
+    run(&mut app, 10, InputIntent::default());
+
+    for entity in [first, second] {
+        assert!(get::<LinearVelocity>(&app, entity).y < 0.0);
+    }
+    assert!(get::<Position>(&app, first).y < start);
+}
+
+#[test]
+fn dead_players_stay_put() {
+    let mut app = headless_app(2);
+    let alive = player(&mut app, 0);
+    let dead = player(&mut app, 1);
+    app.world.entity_mut(dead).insert(Dead);
+
+    run(
+        &mut app,
+        10,
+        InputIntent {
+            movement: 1.0,
+            ..default()
+        },
+    );
+
+    assert!(get::<LinearVelocity>(&app, alive).x > 0.0);
+    assert_eq!(get::<LinearVelocity>(&app, dead).x, 0.0);
+}
+
+#[test]
+fn enemy_health_scales_with_player_count() {
+    let max_health = |player_count| {
+        let mut app = headless_app(player_count);
+        let dummy =
+            app.world
+                .run_system_once(|mut commands: Commands, assets: Res<AssetServer>| {
+                    spawn_dummy(&mut commands, &assets, Vec2::ZERO)
+                });
+        step(&mut app, InputIntent::default());
+        app.world.get::<Health>(dummy).unwrap().max
+    };
+
+    let solo = max_health(1);
+    assert_eq!(max_health(4), solo * (1.0 + 3.0 * PARTY_HEALTH_SCALING));
+}
@@ -0,0 +1,111 @@
+//! Melee swings: who they hit and how often.
+mod common;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_xpbd_2d::prelude::*;
+use common::*;
+use risk_of_rust::game::camera::ScreenShakeEvent;
+use risk_of_rust::game::combat::{Health, Team};
+use risk_of_rust::game::enemy::dummy::spawn_dummy;
+use risk_of_rust::game::hitbox::{Hitbox, HurtboxBundle, MeleeAttack, MeleeSwing};
+use risk_of_rust::game::net::protocol::InputIntent;
+
+const TARGET_HEALTH: f32 = 100.0;
+
+/// A character of `team` standing still at `position`, with a hurtbox like the player's.
+fn target(app: &mut App, team: Team, position: Vec2) -> Entity {
+    app.world
+        .spawn((
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.0))),
+            RigidBody::Kinematic,
+            Health::new(TARGET_HEALTH),
+            team,
+        ))
+        .with_children(|parent| {
+            parent.spawn(HurtboxBundle::new(team, Collider::cuboid(6.0, 11.0)));
+        })
+        .id()
+}
+
+fn damage_taken(app: &App, entity: Entity) -> f32 {
+    let health = app.world.get::<Health>(entity).unwrap();
+    health.max - health.current
+}
+
+fn hitboxes(app: &mut App) -> usize {
+    app.world
+        .query_filtered::<(), With<Hitbox>>()
+        .iter(&app.world)
+        .count()
+}
+
+#[test]
+fn dummy_slam_hits_each_player_once() {
+    let mut app = empty_app();
+    let dummy = app
+        .world
+        .run_system_once(|mut commands: Commands, assets: Res<AssetServer>| {
+            spawn_dummy(&mut commands, &assets, Vec2::ZERO)
+        });
+    let left = target(&mut app, Team::Player, Vec2::new(-10.0, 0.0));
+    let right = target(&mut app, Team::Player, Vec2::new(10.0, 0.0));
+
+    step(&mut app, InputIntent::default());
+    let damage = app.world.get::<MeleeSwing>(dummy).unwrap().attack.damage;
+
+    // Both stay inside the hitbox for the whole swing, but the dummy can't swing again yet.
+    run(&mut app, 60, InputIntent::default());
+    assert_eq!(damage_taken(&app, left), damage);
+    assert_eq!(damage_taken(&app, right), damage);
+    assert!(app.world.get::<MeleeSwing>(dummy).is_none());
+    assert_eq!(hitboxes(&mut app), 0);
+}
+
+#[test]
+fn dummy_slam_shakes_the_screen() {
+    let mut app = empty_app();
+    app.world
+        .run_system_once(|mut commands: Commands, assets: Res<AssetServer>| {
+            spawn_dummy(&mut commands, &assets, Vec2::ZERO)
+        });
+    target(&mut app, Team::Player, Vec2::new(10.0, 0.0));
+
+    let mut reader = app
+        .world
+        .resource::<Events<ScreenShakeEvent>>()
+        .get_reader();
+    let mut shakes = Vec::new();
+    for _ in 0..60 {
+        step(&mut app, InputIntent::default());
+        let events = app.world.resource::<Events<ScreenShakeEvent>>();
+        shakes.extend(reader.read(events).map(|shake| shake.trauma));
+    }
+    assert_eq!(shakes.len(), 1);
+    assert!(shakes[0] > 0.0);
+}
+
+#[test]
+fn player_hitboxes_only_hit_enemies() {
+    let mut app = empty_app();
+    let attacker = target(&mut app, Team::Player, Vec2::ZERO);
+    let ally = target(&mut app, Team::Player, Vec2::new(-10.0, 0.0));
+    let enemy = target(&mut app, Team::Enemy, Vec2::new(10.0, 0.0));
+
+    app.world
+        .entity_mut(attacker)
+        .insert(MeleeSwing::new(MeleeAttack {
+            active_frames: 0..=0,
+            offset: Vec2::ZERO,
+            size: Vec2::new(40.0, 24.0),
+            damage: 10.0,
+            proc_coefficient: 1.0,
+            knockback: Vec2::ZERO,
+            hitstun: 0.0,
+            screen_shake: 0.0,
+        }));
+    run(&mut app, 30, InputIntent::default());
+
+    assert_eq!(damage_taken(&app, enemy), 10.0);
+    assert_eq!(damage_taken(&app, ally), 0.0);
+    assert_eq!(damage_taken(&app, attacker), 0.0);
+}
@@ -0,0 +1,101 @@
+//! A build without the `dev` feature, as the game is released, must not carry debug tooling.
+mod common;
+
+use bevy::prelude::*;
+use risk_of_rust::game::net::NetMode;
+use risk_of_rust::game::replay::ReplayMode;
+use risk_of_rust::{build_app, HeadlessPlugin};
+
+/// What the names of debug plugins, systems and resources contain.
+const DEBUG_TOOLING: [&str; 5] = ["::dev::", "::console::", "inspector", "egui", "::debug::"];
+
+/// The app exactly as the binary builds it, only without a window.
+fn game_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(HeadlessPlugin);
+    build_app(&mut app, NetMode::Offline, ReplayMode::Off);
+    app
+}
+
+/// The systems and resources (or other components) of `app` that belong to debug tooling.
+///
+/// Systems are only listed until their schedule first runs, so the app mustn't be updated.
+fn debug_tooling(app: &App) -> Vec<String> {
+    let systems = app
+        .world
+        .resource::<Schedules>()
+        .iter()
+        .flat_map(|(_, schedule)| schedule.graph().systems())
+        .map(|(_, system, _)| system.name().to_string());
+    let components = app
+        .world
+        .components()
+        .iter()
+        .map(|component| component.name().to_string());
+
+    systems
+        .chain(components)
+        .filter(|name| DEBUG_TOOLING.iter().any(|debug| name.contains(debug)))
+        .collect()
+}
+
+#[cfg(not(feature = "dev"))]
+#[test]
+fn no_debug_tooling_without_the_dev_feature() {
+    let app = game_app();
+    let debug_tooling = debug_tooling(&app);
+    assert!(debug_tooling.is_empty(), "{debug_tooling:?}");
+}
+
+/// Makes sure [`debug_tooling`] would notice the tooling if it was built in.
+#[cfg(feature = "dev")]
+#[test]
+fn debug_tooling_with_the_dev_feature() {
+    use bevy_inspector_egui::quick::WorldInspectorPlugin;
+    use bevy_xpbd_2d::prelude::*;
+    use risk_of_rust::dev::DevPlugin;
+    use risk_of_rust::game::console::{ConsoleCommands, ConsolePlugin};
+
+    let app = game_app();
+    assert!(app.is_plugin_added::<DevPlugin>());
+    assert!(app.is_plugin_added::<WorldInspectorPlugin>());
+    assert!(app.is_plugin_added::<PhysicsDebugPlugin>());
+    assert!(app.is_plugin_added::<ConsolePlugin>());
+    assert!(app.world.contains_resource::<ConsoleCommands>());
+
+    let debug_tooling = debug_tooling(&app);
+    for name in ["ConsoleCommands", "toggle_debug_view"] {
+        assert!(
+            debug_tooling.iter().any(|tooling| tooling.contains(name)),
+            "{name} not in {debug_tooling:?}"
+        );
+    }
+}
+
+/// R and L used to flip gravity and give XP, the console does that now.
+#[test]
+fn old_debug_hotkeys_do_nothing() {
+    use bevy::input::{keyboard::KeyboardInput, ButtonState};
+    use bevy_xpbd_2d::prelude::*;
+    use common::*;
+    use risk_of_rust::game::net::protocol::InputIntent;
+    use risk_of_rust::game::player::PlayerXp;
+    use risk_of_rust::GRAVITY;
+
+    let mut app = headless_app(1);
+    let player = player(&mut app, 0);
+    let xp = app.world.get::<PlayerXp>(player).unwrap().0;
+
+    for key_code in [KeyCode::R, KeyCode::L] {
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state: ButtonState::Pressed,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+    run(&mut app, 2, InputIntent::default());
+
+    assert_eq!(app.world.resource::<Gravity>().0, GRAVITY);
+    assert_eq!(app.world.get::<PlayerXp>(player).unwrap().0, xp);
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::fs;
+
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use common::*;
+use risk_of_rust::game::input::Action;
+use risk_of_rust::game::net::protocol::InputIntent;
+use risk_of_rust::game::player::PlayerIndex;
+use risk_of_rust::game::replay::{Replay, ReplayPlayer};
+use risk_of_rust::AppState;
+
+/// A short run walking both ways and jumping, kept as a regression fixture.
+const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/replays/walk_and_jump.replay"
+);
+/// Where the players of [`FIXTURE`] end up, one `index x y velocity_x velocity_y` line each.
+///
+/// Rewritten instead of checked when `UPDATE_FIXTURES` is set, after a change that is meant to
+/// alter the simulation.
+const FIXTURE_END: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/replays/walk_and_jump.end"
+);
+
+fn walk(movement: f32) -> InputIntent {
+    InputIntent {
+        movement,
+        ..default()
+    }
+}
+
+/// The position and velocity of every player, by index.
+fn player_states(app: &mut App) -> Vec<(u32, Vec2, Vec2)> {
+    let mut states: Vec<_> = app
+        .world
+        .query::<(&PlayerIndex, &Position, &LinearVelocity)>()
+        .iter(&app.world)
+        .map(|(index, position, velocity)| (index.0, position.0, velocity.0))
+        .collect();
+    states.sort_by_key(|(index, _, _)| *index);
+    states
+}
+
+/// Plays `replay` to the end and returns where the players ended up.
+fn play_to_end(replay: &Replay) -> Vec<(u32, Vec2, Vec2)> {
+    let mut app = replay_app(replay.clone());
+    // The first update only starts the run.
+    for _ in 0..=replay.ticks.len() {
+        app.update();
+    }
+    let states = player_states(&mut app);
+    app.update();
+    assert!(!app.world.contains_resource::<ReplayPlayer>());
+    states
+}
+
+#[test]
+fn recorded_run_plays_back_exactly() {
+    let path = std::env::temp_dir().join(format!("risk-of-rust-{}.replay", std::process::id()));
+    let mut app = recording_app(2, &path);
+    run(&mut app, 30, walk(1.0));
+    step(&mut app, press(Action::Jump));
+    run(&mut app, 40, walk(-0.5));
+    let recorded = player_states(&mut app);
+
+    app.world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::GameOver);
+    app.update();
+    let replay = Replay::load(&path).expect("the run wasn't saved");
+    fs::remove_file(&path).unwrap();
+
+    // The update that starts the run, the one `recording_app` runs on its own, then the
+    // scripted steps. The replay also has the step the run ended on, which is left out.
+    let mut app = replay_app(replay);
+    for _ in 0..1 + 1 + 30 + 1 + 40 {
+        app.update();
+    }
+    assert_eq!(player_states(&mut app), recorded);
+}
+
+fn format_states(states: &[(u32, Vec2, Vec2)]) -> String {
+    states
+        .iter()
+        .map(|(index, position, velocity)| {
+            format!(
+                "{index} {} {} {} {}\n",
+                position.x, position.y, velocity.x, velocity.y
+            )
+        })
+        .collect()
+}
+
+fn parse_states(contents: &str) -> Vec<(u32, Vec2, Vec2)> {
+    contents
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let float = |i: usize| fields[i].parse::<f32>().unwrap();
+            (
+                fields[0].parse().unwrap(),
+                Vec2::new(float(1), float(2)),
+                Vec2::new(float(3), float(4)),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn fixture_plays_back_the_same_every_time() {
+    let replay = Replay::load(FIXTURE.as_ref()).unwrap();
+    assert_eq!(replay.player_count, 1);
+    let states = play_to_end(&replay);
+    assert_eq!(play_to_end(&replay), states);
+
+    if std::env::var_os("UPDATE_FIXTURES").is_some() {
+        fs::write(FIXTURE_END, format_states(&states)).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(FIXTURE_END).unwrap();
+    assert_eq!(states, parse_states(&expected));
+}